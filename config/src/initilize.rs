@@ -2,11 +2,20 @@ use super::{Config, ConfigContainer};
 use lazy_static;
 use lazy_static::lazy_static as compute_static;
 use rusqlite::{self, Connection};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 pub const TB_FAVOURATES_MUSIC: &str = "favourates_music";
 pub const TB_FAVOURATES_PLAYLIST: &str = "favourates_playlist";
 pub const TB_FAVOURATES_ARTIST: &str = "favourates_artist";
+pub const TB_SERVER_HEALTH: &str = "server_health";
+pub const TB_RESPONSE_CACHE: &str = "response_cache";
+pub const TB_CAPTIONS: &str = "captions";
+pub const TB_ACCOUNT_TOKEN: &str = "account_token";
+pub const TB_SEEN_RELEASE: &str = "seen_release";
+
+// Shared handle to the storage. Fetcher keeps a clone of this so that it can be given a storage
+// other than STORAGE
+pub type Storage = Arc<Mutex<Connection>>;
 
 compute_static! {
     pub static ref CONFIG: Config = {
@@ -30,9 +39,9 @@ compute_static! {
         }
     };
 
-    pub static ref STORAGE: Mutex<Connection> = {
+    pub static ref STORAGE: Storage = {
         match ConfigContainer::give_me_storage() {
-            Some(conn) => Mutex::new(conn),
+            Some(conn) => Arc::new(Mutex::new(conn)),
             None => {
                 eprintln!("A valid storage is required for startup. Exiting..");
                 std::process::exit(1);
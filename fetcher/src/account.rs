@@ -0,0 +1,107 @@
+use config::initilize::{Storage, TB_ACCOUNT_TOKEN};
+
+// Token of invidious account is kept per server as the account only exist in the server it was
+// created in. Request on behalf of the account is only sent to the servers that have a token.
+// Token is what invidious returns from `/authorize_token` and is sent as bearer token as is.
+// See: https://docs.invidious.io/api/authenticated-endpoints/
+
+// Read the token of given servers from storage in same order. None for server that was never
+// logged in to
+pub fn load(storage: &Storage, urls: impl Iterator<Item = impl AsRef<str>>) -> Vec<Option<String>> {
+    let conn = storage.lock().unwrap();
+    let query = format!(
+        "
+        SELECT token FROM {tb_name} WHERE url = :url
+    ",
+        tb_name = TB_ACCOUNT_TOKEN
+    );
+
+    let mut stmt = match conn.prepare(&query) {
+        Ok(val) => Some(val),
+        Err(err) => {
+            eprintln!(
+                "Error preparing select statement for account token. Error: {err}",
+                err = err
+            );
+            None
+        }
+    };
+
+    urls.map(|url| {
+        stmt.as_mut().and_then(|stmt| {
+            stmt.query_row(&[(":url", url.as_ref())], |row| row.get(0))
+                .ok()
+        })
+    })
+    .collect()
+}
+
+// Remember the token for the server replacing the previous one if any
+pub fn save(storage: &Storage, url: &str, token: &str) -> rusqlite::Result<()> {
+    let query = format!(
+        "
+        INSERT OR REPLACE INTO {tb_name}
+        (url, token)
+        VALUES
+        (:url, :token)
+    ",
+        tb_name = TB_ACCOUNT_TOKEN
+    );
+
+    storage
+        .lock()
+        .unwrap()
+        .execute(&query, &[(":url", url), (":token", token)])
+        .map(|_| ())
+}
+
+// Forget the token of the server. Returns false if there was none
+pub fn remove(storage: &Storage, url: &str) -> rusqlite::Result<bool> {
+    let query = format!(
+        "
+        DELETE FROM {tb_name} WHERE url = :url
+    ",
+        tb_name = TB_ACCOUNT_TOKEN
+    );
+
+    storage
+        .lock()
+        .unwrap()
+        .execute(&query, &[(":url", url)])
+        .map(|removed| removed > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn token_is_kept_per_server() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        config::ConfigContainer::create_tables(&conn).unwrap();
+        let storage: Storage = Arc::new(Mutex::new(conn));
+
+        save(&storage, "https://one.example/api/v1", "first").unwrap();
+        save(&storage, "https://two.example/api/v1", "second").unwrap();
+        save(&storage, "https://one.example/api/v1", "renewed").unwrap();
+
+        let urls = [
+            "https://one.example/api/v1",
+            "https://other.example/api/v1",
+            "https://two.example/api/v1",
+        ];
+        assert_eq!(
+            load(&storage, urls.iter()),
+            vec![
+                Some("renewed".to_string()),
+                None,
+                Some("second".to_string())
+            ]
+        );
+
+        assert_eq!(remove(&storage, urls[2]), Ok(true));
+        assert_eq!(remove(&storage, urls[2]), Ok(false));
+        assert_eq!(load(&storage, urls[2..].iter()), vec![None]);
+    }
+}
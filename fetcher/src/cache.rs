@@ -0,0 +1,251 @@
+use crate::source::ApiFlavour;
+use config::initilize::{Storage, TB_RESPONSE_CACHE};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+// Kind of request whose response is cached. Each kind have its own ttl in config
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Trending,
+    Search,
+    Playlist,
+}
+
+impl Kind {
+    // Seconds until which the response of this kind is fresh
+    pub fn ttl(self, settings: &config::Cache) -> u64 {
+        match self {
+            Kind::Trending => settings.trending_ttl,
+            Kind::Search => settings.search_ttl,
+            Kind::Playlist => settings.playlist_ttl,
+        }
+    }
+}
+
+// Keys whose stale response is being refetched in background. Fetchers built with
+// FetcherBuilder::share_with() have the same set so that scrolling a stale list or prefetching
+// it do not send the same request several times at once
+pub type Refreshing = Arc<Mutex<HashSet<String>>>;
+
+// Key is being refreshed as long as this is alive. Dropped even when the refresh panics
+pub struct RefreshGuard {
+    refreshing: Refreshing,
+    key: String,
+}
+
+impl Drop for RefreshGuard {
+    fn drop(&mut self) {
+        self.refreshing.lock().unwrap().remove(&self.key);
+    }
+}
+
+// Mark the key as being refreshed. None if it already is
+pub fn start_refresh(refreshing: &Refreshing, key: &str) -> Option<RefreshGuard> {
+    if !refreshing.lock().unwrap().insert(key.to_string()) {
+        return None;
+    }
+    Some(RefreshGuard {
+        refreshing: Refreshing::clone(refreshing),
+        key: key.to_string(),
+    })
+}
+
+// Cached response body and when it was fetched (unix seconds)
+pub struct Entry {
+    pub body: Vec<u8>,
+    pub fetched_at: u64,
+}
+
+impl Entry {
+    // Entry that is not fresh is still served but should be refetched
+    pub fn is_fresh(&self, ttl: u64, now: u64) -> bool {
+        self.fetched_at.saturating_add(ttl) > now
+    }
+}
+
+// Key under which response of given request is cached. Server itself is not part of the key so
+// that response from any server of same api can be reused. Response of different api are in
+// different shape so api is part of the key.
+// query is not url encoded here, pairs are seperated by newline which never appear in any of
+// the value we send
+pub fn key(api: ApiFlavour, path: &str, query: &[(&str, &str)]) -> String {
+    let mut key = format!("{:?}:{}", api, path);
+    for (name, value) in query {
+        key.push('\n');
+        key.push_str(name);
+        key.push('=');
+        key.push_str(value);
+    }
+    key
+}
+
+// Read the cached response of given key. This also marks the entry as used so that it is evicted
+// last. None if caching is disabled or there is no such entry
+pub fn get(storage: &Storage, settings: &config::Cache, key: &str) -> Option<Entry> {
+    if !settings.enabled {
+        return None;
+    }
+
+    let conn = storage.lock().unwrap();
+    let query = format!(
+        "SELECT body, fetched_at FROM {tb_name} WHERE key = ?1",
+        tb_name = TB_RESPONSE_CACHE
+    );
+    let entry = conn
+        .query_row(&query, [key], |row| {
+            Ok(Entry {
+                body: row.get(0)?,
+                fetched_at: row.get::<_, i64>(1)? as u64,
+            })
+        })
+        .ok()?;
+
+    let query = format!(
+        "UPDATE {tb_name} SET last_used = ?1 WHERE key = ?2",
+        tb_name = TB_RESPONSE_CACHE
+    );
+    // failing to update last_used only affect the order of eviction. Not worth reporting
+    let _ = conn.execute(&query, rusqlite::params![crate::health::now() as i64, key]);
+
+    Some(entry)
+}
+
+// Save the response body under given key replacing the previous one if any. Least recently
+// used entries are then removed until everything fits in max_size_kb
+pub fn put(storage: &Storage, settings: &config::Cache, key: &str, body: &[u8]) {
+    if !settings.enabled {
+        return;
+    }
+
+    let conn = storage.lock().unwrap();
+    let now = crate::health::now() as i64;
+    let query = format!(
+        "
+        INSERT OR REPLACE INTO {tb_name}
+        (key, body, size, fetched_at, last_used)
+        VALUES
+        (?1, ?2, ?3, ?4, ?4)
+    ",
+        tb_name = TB_RESPONSE_CACHE
+    );
+    if let Err(err) = conn.execute(&query, rusqlite::params![key, body, body.len() as i64, now]) {
+        eprintln!(
+            "Cannot cache response of {key}. Error: {err}",
+            key = key,
+            err = err
+        );
+        return;
+    }
+
+    if let Err(err) = evict(&conn, settings.max_size_kb * 1024) {
+        eprintln!("Cannot evict old cached response. Error: {err}", err = err);
+    }
+}
+
+fn evict(conn: &rusqlite::Connection, max_size: u64) -> rusqlite::Result<()> {
+    let query = format!(
+        "SELECT key, size FROM {tb_name} ORDER BY last_used DESC",
+        tb_name = TB_RESPONSE_CACHE
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let entries = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let query = format!(
+        "DELETE FROM {tb_name} WHERE key = ?1",
+        tb_name = TB_RESPONSE_CACHE
+    );
+    for key in over_size(&entries, max_size) {
+        conn.execute(&query, [key])?;
+    }
+    Ok(())
+}
+
+// `entries` are (key, size) ordered from most recently used. Returns the keys that do not fit
+// in `max_size` once every more recently used entry is kept
+fn over_size(entries: &[(String, u64)], max_size: u64) -> Vec<&str> {
+    let mut total = 0;
+    entries
+        .iter()
+        .filter(|(_, size)| {
+            total += size;
+            total > max_size
+        })
+        .map(|(key, _)| key.as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_key_is_refreshed_once_at_a_time() {
+        let refreshing = Refreshing::default();
+        let guard = start_refresh(&refreshing, "trending").unwrap();
+        assert!(start_refresh(&refreshing, "trending").is_none());
+        assert!(start_refresh(&refreshing, "search").is_some());
+
+        drop(guard);
+        assert!(start_refresh(&refreshing, "trending").is_some());
+    }
+
+    #[test]
+    fn key_depends_on_api_path_and_query() {
+        let search = key(
+            ApiFlavour::Invidious,
+            "/search",
+            &[("q", "abc"), ("page", "1")],
+        );
+        assert_ne!(
+            search,
+            key(ApiFlavour::Piped, "/search", &[("q", "abc"), ("page", "1")])
+        );
+        assert_ne!(
+            search,
+            key(
+                ApiFlavour::Invidious,
+                "/search",
+                &[("q", "abc"), ("page", "2")]
+            )
+        );
+        assert_ne!(
+            search,
+            key(
+                ApiFlavour::Invidious,
+                "/trending",
+                &[("q", "abc"), ("page", "1")]
+            )
+        );
+        assert_eq!(
+            search,
+            key(
+                ApiFlavour::Invidious,
+                "/search",
+                &[("q", "abc"), ("page", "1")]
+            )
+        );
+    }
+
+    #[test]
+    fn evicts_least_recently_used_beyond_size() {
+        let entries = vec![
+            ("new".to_string(), 40),
+            ("mid".to_string(), 50),
+            ("old".to_string(), 20),
+            ("oldest".to_string(), 5),
+        ];
+        assert_eq!(over_size(&entries, 100), vec!["old", "oldest"]);
+        assert!(over_size(&entries, 1000).is_empty());
+
+        let entry = Entry {
+            body: Vec::new(),
+            fetched_at: 100,
+        };
+        assert!(entry.is_fresh(60, 150));
+        assert!(!entry.is_fresh(60, 160));
+    }
+}
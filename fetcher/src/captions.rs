@@ -0,0 +1,228 @@
+use crate::source::ApiFlavour;
+use config::initilize::{Storage, TB_CAPTIONS};
+use std::time::Duration;
+
+// Single caption track of a music. `url` is where the source fetches the content of this track
+// from and is only meaningful to the source of `api` that returned it
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaptionTrack {
+    // Name to show in language selector. eg: `English (auto-generated)`
+    pub label: String,
+    pub language: String,
+    pub url: String,
+    // None for the track read from storage. Its content is also there so no source is needed
+    pub api: Option<ApiFlavour>,
+}
+
+// Single timed line of caption
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaptionLine {
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+}
+
+// Parse the content of WebVTT file to the lines of caption. Styling tags inside the text are
+// removed and text lines of a cue are joined with a space. Youtube auto-generated captions
+// repeat the previous cue as the first line of every cue, so that line is dropped and
+// consecutive cues with the same text are merged into one to keep the lines readable
+pub fn parse_webvtt(vtt: &str) -> Vec<CaptionLine> {
+    let mut lines: Vec<CaptionLine> = Vec::new();
+
+    for block in vtt.replace("\r\n", "\n").split("\n\n") {
+        let mut block_lines = block.lines().skip_while(|line| !line.contains("-->"));
+        let (start, end) = match block_lines.next().and_then(parse_timing) {
+            Some(timing) => timing,
+            // header, NOTE, STYLE or anything else that is not a cue
+            None => continue,
+        };
+
+        let mut text_lines = block_lines
+            .map(strip_tags)
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let rolled = match (lines.last(), text_lines.first()) {
+            (Some(last), Some(first)) => text_lines.len() > 1 && last.text == *first,
+            _ => false,
+        };
+        if rolled {
+            text_lines.remove(0);
+        }
+        let text = text_lines.join(" ");
+        if text.is_empty() {
+            continue;
+        }
+
+        match lines.last_mut() {
+            Some(last) if last.text == text => last.end = end,
+            _ => lines.push(CaptionLine { start, end, text }),
+        }
+    }
+
+    lines
+}
+
+// Index of the line being sung at `time`. That is the last line that started before `time`
+pub fn current_line(lines: &[CaptionLine], time: Duration) -> Option<usize> {
+    lines.iter().rposition(|line| line.start <= time)
+}
+
+// `00:01:02.500 --> 00:01:04.000 align:start position:0%` to (start, end)
+fn parse_timing(line: &str) -> Option<(Duration, Duration)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_time(start.trim())?, parse_time(end)?))
+}
+
+// `hh:mm:ss.ttt` or `mm:ss.ttt`
+fn parse_time(time: &str) -> Option<Duration> {
+    let (time, millis) = time.split_once('.')?;
+    let mut seconds: u64 = 0;
+    for part in time.split(':') {
+        seconds = seconds * 60 + part.parse::<u64>().ok()?;
+    }
+    Some(Duration::from_secs(seconds) + Duration::from_millis(millis.parse().ok()?))
+}
+
+fn strip_tags(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut in_tag = false;
+    for c in line.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+// Content of caption track saved earlier. Captions do not change so there is no expiry and
+// these are never evicted like the response cache
+pub fn load(storage: &Storage, music_id: &str, label: &str) -> Option<String> {
+    let query = format!(
+        "SELECT body FROM {tb_name} WHERE id = ?1 AND label = ?2",
+        tb_name = TB_CAPTIONS
+    );
+    storage
+        .lock()
+        .unwrap()
+        .query_row(&query, [music_id, label], |row| row.get(0))
+        .ok()
+}
+
+pub fn save(storage: &Storage, music_id: &str, track: &CaptionTrack, body: &str) {
+    let query = format!(
+        "
+        INSERT OR REPLACE INTO {tb_name}
+        (id, label, language, body)
+        VALUES
+        (?1, ?2, ?3, ?4)
+    ",
+        tb_name = TB_CAPTIONS
+    );
+    let res = storage
+        .lock()
+        .unwrap()
+        .execute(&query, [music_id, &track.label, &track.language, body]);
+    if let Err(err) = res {
+        eprintln!(
+            "Cannot save captions of {id}. Error: {err}",
+            id = music_id,
+            err = err
+        );
+    }
+}
+
+// Tracks of given music whose content is saved. Used when the tracks cannot be fetched from
+// the server. eg: when offline
+pub fn saved_tracks(storage: &Storage, music_id: &str) -> Vec<CaptionTrack> {
+    let query = format!(
+        "SELECT label, language FROM {tb_name} WHERE id = ?1",
+        tb_name = TB_CAPTIONS
+    );
+    let conn = storage.lock().unwrap();
+    let tracks = conn.prepare(&query).and_then(|mut stmt| {
+        stmt.query_map([music_id], |row| {
+            Ok(CaptionTrack {
+                label: row.get(0)?,
+                language: row.get(1)?,
+                url: String::new(),
+                api: None,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()
+    });
+    tracks.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_auto_generated_webvtt() {
+        let vtt = "WEBVTT\n\
+            Kind: captions\n\
+            Language: en\n\
+            \n\
+            00:00:01.000 --> 00:00:03.500 align:start position:0%\n\
+            first<00:00:01.500><c> line</c>\n\
+            \n\
+            00:00:03.500 --> 00:00:03.510 align:start position:0%\n\
+            first line\n\
+            \n\
+            2\n\
+            00:01:03.510 --> 01:00:05.000\n\
+            first line\n\
+            second line\n";
+
+        let lines = parse_webvtt(vtt);
+        assert_eq!(
+            lines,
+            vec![
+                CaptionLine {
+                    start: Duration::from_millis(1000),
+                    end: Duration::from_millis(3510),
+                    text: "first line".to_string(),
+                },
+                CaptionLine {
+                    start: Duration::from_millis(63510),
+                    end: Duration::from_secs(3605),
+                    text: "second line".to_string(),
+                },
+            ]
+        );
+
+        assert_eq!(current_line(&lines, Duration::from_secs(0)), None);
+        assert_eq!(current_line(&lines, Duration::from_secs(2)), Some(0));
+        assert_eq!(current_line(&lines, Duration::from_secs(64)), Some(1));
+    }
+
+    #[test]
+    fn parse_manual_webvtt() {
+        let vtt = "WEBVTT\n\
+            \n\
+            00:00:01.000 --> 00:00:04.000\n\
+            <i>Hello darkness</i>\n\
+            my old friend\n\
+            \n\
+            00:00:04.000 --> 00:00:08.000\n\
+            I've come to talk\n\
+            with you again\n";
+
+        let text = parse_webvtt(vtt)
+            .into_iter()
+            .map(|line| line.text)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            text,
+            vec![
+                "Hello darkness my old friend",
+                "I've come to talk with you again"
+            ]
+        );
+    }
+}
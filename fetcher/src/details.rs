@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+// Everything about a single music as shown in details window. Sources convert their response
+// of single video to this. Counts that the server do not tell about are 0 and texts are empty
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VideoDetails {
+    pub id: String,
+    pub title: String,
+    // name and id of the channel that uploaded this music
+    pub channel: String,
+    pub channel_id: String,
+    // subscriber count of the channel as given by server. eg: 1.2M
+    pub subscribers: String,
+    pub views: u64,
+    pub likes: u64,
+    // publish date as given by server. This may be the date or something like `2 years ago`
+    pub published: String,
+    pub genre: String,
+    // plain text description. Html from server (if any) is already converted to text
+    pub description: String,
+}
+
+// A point in the music as written in description. eg: `03:25 Second song`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chapter {
+    pub start: Duration,
+    pub title: String,
+}
+
+impl VideoDetails {
+    // Chapters from the timestamps written in description. Every line that contains a timestamp
+    // is a chapter starting at that time and rest of that line is its title.
+    // Timestamps are `m:ss`, `mm:ss` or `h:mm:ss` optionally inside brackets
+    pub fn chapters(&self) -> Vec<Chapter> {
+        self.description
+            .lines()
+            .filter_map(|line| {
+                let (word, start) = line
+                    .split_whitespace()
+                    .find_map(|word| parse_timestamp(word).map(|start| (word, start)))?;
+                let title = line
+                    .replacen(word, "", 1)
+                    .trim_matches(|c: char| c.is_whitespace() || "-–|:".contains(c))
+                    .to_string();
+                Some(Chapter { start, title })
+            })
+            .collect()
+    }
+}
+
+fn parse_timestamp(word: &str) -> Option<Duration> {
+    let word = word.trim_matches(|c| "()[]".contains(c));
+    let parts: Vec<&str> = word.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        return None;
+    }
+
+    let mut seconds = 0;
+    for (index, part) in parts.iter().enumerate() {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let value: u64 = part.parse().ok()?;
+        // Only the first part can be of any length. Minutes and seconds after it are 2 digits
+        if index > 0 && (part.len() != 2 || value >= 60) {
+            return None;
+        }
+        seconds = seconds * 60 + value;
+    }
+    Some(Duration::from_secs(seconds))
+}
+
+// Description from some server is in html. Convert it to plain text by keeping line breaks and
+// dropping every other tag
+pub fn html_to_text(html: &str) -> String {
+    let html = html
+        .replace("<br>", "\n")
+        .replace("<br/>", "\n")
+        .replace("<br />", "\n");
+
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chapters_from_description() {
+        let details = VideoDetails {
+            description: "Tracklist:\n\
+                0:00 Intro\n\
+                [03:25] - Second song\n\
+                Third song | 1:02:05\n\
+                ratio 16:9 and 10:61 are not timestamps\n\
+                http://example.com"
+                .to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            details.chapters(),
+            vec![
+                Chapter {
+                    start: Duration::from_secs(0),
+                    title: "Intro".to_string()
+                },
+                Chapter {
+                    start: Duration::from_secs(205),
+                    title: "Second song".to_string()
+                },
+                Chapter {
+                    start: Duration::from_secs(3725),
+                    title: "Third song".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn html_description_to_text() {
+        assert_eq!(
+            html_to_text("Line &amp; one<br>see <a href=\"/watch?v=x&t=65\">1:05</a>"),
+            "Line & one\nsee 1:05"
+        );
+    }
+}
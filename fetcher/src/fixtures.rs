@@ -0,0 +1,89 @@
+use crate::source::ApiFlavour;
+use crate::{cache, FetchError, ReturnAction};
+use std::path::Path;
+
+// Name of the file in which response of given request is recorded. Like the cache key, server
+// is not part of the name so that response recorded from one server can be replayed with any
+// server of same api. Query is only hashed as it can be long and have characters that are not
+// allowed in file name. eg: `invidious-trending-1a2b3c4d5e6f7a8b.json`
+pub fn file_name(api: ApiFlavour, path: &str, query: &[(&str, &str)]) -> String {
+    let readable = path
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    let readable = readable.trim_matches('_');
+
+    format!(
+        "{api}-{path}-{hash:016x}.json",
+        api = format!("{:?}", api).to_lowercase(),
+        path = &readable[..std::cmp::min(readable.len(), 60)],
+        hash = hash(&cache::key(api, path, query))
+    )
+}
+
+// FNV-1a. Unlike the hasher of std this is guaranteed to give the same hash in every build so
+// that file recorded once can always be found
+fn hash(key: &str) -> u64 {
+    key.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+// Response recorded in file `name` of `dir`. Request that was never recorded fails as if the
+// server could not be reached so that rest of the fetcher handles it same way
+pub fn read(dir: &str, name: &str) -> Result<Vec<u8>, ReturnAction> {
+    let path = Path::new(dir).join(name);
+    std::fs::read(&path).map_err(|_| {
+        ReturnAction::Failed(FetchError::NotRecorded {
+            file: path.to_string_lossy().to_string(),
+        })
+    })
+}
+
+// Write the response to file `name` of `dir` replacing the one recorded earlier if any
+pub fn write(dir: &str, name: &str, body: &[u8]) {
+    let res =
+        std::fs::create_dir_all(dir).and_then(|_| std::fs::write(Path::new(dir).join(name), body));
+    if let Err(err) = res {
+        eprintln!(
+            "Cannot record response to {name} in {dir}. Error: {err}",
+            name = name,
+            dir = dir,
+            err = err
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_name_is_stable_and_file_safe() {
+        let trending = file_name(
+            ApiFlavour::Invidious,
+            "/trending",
+            &[("region", "NP"), ("type", "music")],
+        );
+        // Name must not change between builds or the recorded files can not be found anymore
+        assert_eq!(trending, "invidious-trending-3449b5dfa0ac2af8.json");
+        assert_ne!(
+            trending,
+            file_name(
+                ApiFlavour::Invidious,
+                "/trending",
+                &[("region", "US"), ("type", "music")]
+            )
+        );
+
+        let captions = file_name(
+            ApiFlavour::Piped,
+            "https://proxy.example.com/api/timedtext?v=id&lang=en",
+            &[],
+        );
+        assert!(captions.starts_with("piped-https___proxy_example_com_api_timedtext"));
+        assert!(captions
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)));
+    }
+}
@@ -0,0 +1,216 @@
+use config::initilize::{Storage, TB_SERVER_HEALTH};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Latency assumed for a server that was never used. This is kept somewhat high so that a known
+// fast server is preferred but a server that is known to be slow gets a chance to be replaced
+const UNKNOWN_LATENCY_MS: f64 = 1500.0;
+// Weight given to the latest sample while calculating rolling latency
+const LATENCY_WEIGHT: f64 = 0.3;
+// Cooldown after first failure. This is doubled on every consecutive failure
+const BASE_COOLDOWN_SECS: u64 = 30;
+const MAX_COOLDOWN_SECS: u64 = 60 * 60;
+
+// Seconds since unix epoch. Cooldown is stored in this unit so that it is still meaningful
+// when read in next session
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|dur| dur.as_secs())
+        .unwrap_or_default()
+}
+
+// How a single server has been behaving. There is one such record for every server in
+// Fetcher::servers and they are saved in storage so that they are not lost between sessions
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ServerHealth {
+    pub successes: u32,
+    pub failures: u32,
+    // failures since the last success. Used to grow the cooldown period
+    pub consecutive_failures: u32,
+    // Rolling (exponentially weighted) average of response time. None if never succeeded
+    pub latency_ms: Option<f64>,
+    // Unix time in seconds until which this server should not be used
+    pub cooldown_until: u64,
+}
+
+impl ServerHealth {
+    pub fn record_success(&mut self, latency: Duration) {
+        let sample = latency.as_secs_f64() * 1000.0;
+        self.latency_ms = Some(match self.latency_ms {
+            Some(prev) => prev + LATENCY_WEIGHT * (sample - prev),
+            None => sample,
+        });
+        self.successes = self.successes.saturating_add(1);
+        self.consecutive_failures = 0;
+        self.cooldown_until = 0;
+    }
+
+    pub fn record_failure(&mut self, now: u64) {
+        self.failures = self.failures.saturating_add(1);
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+
+        let exponent = std::cmp::min(self.consecutive_failures - 1, 16);
+        let cooldown = std::cmp::min(BASE_COOLDOWN_SECS << exponent, MAX_COOLDOWN_SECS);
+        self.cooldown_until = now + cooldown;
+    }
+
+    pub fn is_cooling_down(&self, now: u64) -> bool {
+        self.cooldown_until > now
+    }
+
+    // Lower is better. This is the expected latency punished by the ratio of failed request.
+    // One success and one failure is added to the ratio so that a server is not judged by the
+    // only request it has served
+    pub fn score(&self) -> f64 {
+        let latency = self.latency_ms.unwrap_or(UNKNOWN_LATENCY_MS);
+        let failure_ratio =
+            (self.failures as f64 + 1.0) / (self.successes as f64 + self.failures as f64 + 2.0);
+
+        latency * (1.0 + 4.0 * failure_ratio)
+    }
+}
+
+// Choose the healthiest server among `health` for which `allowed` returns true.
+// Servers in cooldown are only chosen when every allowed server is cooling down and in that case
+// the one that recovers first is returned.
+// `after` is the index of last used server. Among equally healthy servers the one next to it is
+// preferred so that the load is still distributed when nothing is known about servers.
+pub fn pick(
+    health: &[ServerHealth],
+    after: usize,
+    now: u64,
+    allowed: impl Fn(usize) -> bool,
+) -> Option<usize> {
+    let len = health.len();
+    let candidates = (1..=len)
+        .map(|offset| (after + offset) % len)
+        .filter(|index| allowed(*index));
+
+    let mut best: Option<usize> = None;
+    let mut best_cooling: Option<usize> = None;
+    for index in candidates {
+        let record = &health[index];
+        if record.is_cooling_down(now) {
+            if best_cooling.map_or(true, |b| health[b].cooldown_until > record.cooldown_until) {
+                best_cooling = Some(index);
+            }
+        } else if best.map_or(true, |b| health[b].score() > record.score()) {
+            best = Some(index);
+        }
+    }
+
+    best.or(best_cooling)
+}
+
+// Health of every server in same order as the servers. Fetchers that send request to same
+// servers share this so that a server one of them found dead is avoided by all of them
+pub type Shared = Arc<Mutex<Vec<ServerHealth>>>;
+
+// Read the health of given servers from storage. Server that have no record are
+// returned with default (unknown) health
+pub fn load(storage: &Storage, urls: impl Iterator<Item = impl AsRef<str>>) -> Vec<ServerHealth> {
+    let conn = storage.lock().unwrap();
+    let query = format!(
+        "
+        SELECT
+        successes, failures, consecutive_failures, latency_ms, cooldown_until
+        FROM {tb_name}
+        WHERE url = :url
+    ",
+        tb_name = TB_SERVER_HEALTH
+    );
+
+    let mut stmt = match conn.prepare(&query) {
+        Ok(val) => Some(val),
+        Err(err) => {
+            eprintln!(
+                "Error preparing select statement for server health. Error: {err}",
+                err = err
+            );
+            None
+        }
+    };
+
+    urls.map(|url| {
+        let record = stmt.as_mut().and_then(|stmt| {
+            stmt.query_row(&[(":url", url.as_ref())], |row| {
+                Ok(ServerHealth {
+                    successes: row.get(0)?,
+                    failures: row.get(1)?,
+                    consecutive_failures: row.get(2)?,
+                    latency_ms: row.get(3)?,
+                    cooldown_until: row.get::<_, i64>(4)? as u64,
+                })
+            })
+            .ok()
+        });
+        record.unwrap_or_default()
+    })
+    .collect()
+}
+
+// Write the health of single server to storage
+pub fn save(storage: &Storage, url: &str, health: &ServerHealth) {
+    let query = format!(
+        "
+        INSERT OR REPLACE INTO {tb_name}
+        (url, successes, failures, consecutive_failures, latency_ms, cooldown_until)
+        VALUES
+        (?1, ?2, ?3, ?4, ?5, ?6)
+    ",
+        tb_name = TB_SERVER_HEALTH
+    );
+
+    let res = storage.lock().unwrap().execute(
+        &query,
+        rusqlite::params![
+            url,
+            health.successes,
+            health.failures,
+            health.consecutive_failures,
+            health.latency_ms,
+            health.cooldown_until as i64,
+        ],
+    );
+    if let Err(err) = res {
+        eprintln!(
+            "Cannot save health of server {url}. Error: {err}",
+            url = url,
+            err = err
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pick_prefers_fast_and_skips_cooling_down() {
+        let mut health = vec![ServerHealth::default(); 3];
+        health[0].record_success(Duration::from_millis(900));
+        health[1].record_success(Duration::from_millis(200));
+        health[2].record_failure(100);
+
+        assert_eq!(pick(&health, 0, 100, |_| true), Some(1));
+        assert_eq!(pick(&health, 0, 100, |index| index != 1), Some(0));
+
+        // Only the server in cooldown is allowed. It is still returned
+        assert_eq!(pick(&health, 0, 100, |index| index == 2), Some(2));
+        assert_eq!(pick(&health, 0, 100, |_| false), None);
+    }
+
+    #[test]
+    fn cooldown_grows_with_consecutive_failures() {
+        let mut health = ServerHealth::default();
+        health.record_failure(0);
+        assert_eq!(health.cooldown_until, BASE_COOLDOWN_SECS);
+        health.record_failure(0);
+        assert_eq!(health.cooldown_until, BASE_COOLDOWN_SECS * 2);
+
+        health.record_success(Duration::from_millis(100));
+        assert!(!health.is_cooling_down(0));
+        assert_eq!(health.consecutive_failures, 0);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::lenient::Lenient;
+use crate::source::ApiFlavour;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+// Public invidious instances along with their uptime as monitored by invidious itself
+pub const INSTANCES_URL: &str = "https://api.invidious.io/instances.json?sort_by=type,health";
+// Instance that was up for less than this percent of last 30 days is not worth trying
+const MIN_UPTIME: f64 = 90.0;
+// Request sent to see if instance is working. Trending is what is shown first on startup and it
+// is disabled or broken on many instances that otherwise look healthy
+const PROBE_PATH: &str = "/trending?type=music&fields=videoId";
+// Instance slower than this is left out even if it would answer eventually
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Single entry of instances.json. The file is a list of [host, instance] pairs
+#[derive(Deserialize)]
+struct InstanceRes {
+    // https, onion or i2p
+    #[serde(rename = "type")]
+    kind: String,
+    uri: String,
+    // null when not known
+    api: Option<bool>,
+    monitor: Option<MonitorRes>,
+}
+
+#[derive(Deserialize)]
+struct MonitorRes {
+    #[serde(rename = "30dRatio")]
+    ratio_30d: Option<RatioRes>,
+}
+
+#[derive(Deserialize)]
+struct RatioRes {
+    // Uptime percentage as string. eg: "99.87"
+    ratio: String,
+}
+
+fn parse(body: &[u8]) -> Result<Vec<InstanceRes>, serde_json::Error> {
+    let instances: Lenient<(String, InstanceRes)> = serde_json::from_slice(body)?;
+    Ok(instances
+        .0
+        .into_iter()
+        .map(|(_host, instance)| instance)
+        .collect())
+}
+
+fn api_url(uri: &str) -> String {
+    format!("{}/api/v1", uri.trim_end_matches('/'))
+}
+
+fn is_same_url(left: &str, right: &str) -> bool {
+    left.trim_end_matches('/') == right.trim_end_matches('/')
+}
+
+// Api url of every instance in instances.json whatever its health is
+pub fn listed(body: &[u8]) -> Result<Vec<String>, serde_json::Error> {
+    Ok(parse(body)?
+        .iter()
+        .map(|instance| api_url(&instance.uri))
+        .collect())
+}
+
+// Api url of every instance in instances.json that is reachable over https, have api enabled
+// and have been up for most of the time. Instance whose uptime is unknown is left out
+pub fn candidates(body: &[u8]) -> Result<Vec<String>, serde_json::Error> {
+    let urls = parse(body)?
+        .into_iter()
+        .filter(|instance| instance.kind == "https" && instance.api == Some(true))
+        .filter(|instance| {
+            let uptime = instance
+                .monitor
+                .as_ref()
+                .and_then(|monitor| monitor.ratio_30d.as_ref())
+                .and_then(|ratio| ratio.ratio.parse::<f64>().ok());
+            uptime.is_some_and(|uptime| uptime >= MIN_UPTIME)
+        })
+        .map(|instance| api_url(&instance.uri))
+        .collect();
+    Ok(urls)
+}
+
+// Server list with `working` public instances in place of the ones in `existing` that are not
+// working anymore. Invidious server that is not `listed` in public instances (eg: own instance)
+// or that have a token in `tokens` (same order as existing) is kept as is. So is server of
+// other api
+pub fn merge(
+    existing: Vec<config::Server>,
+    tokens: &[Option<String>],
+    listed: &[String],
+    working: Vec<String>,
+) -> Vec<config::Server> {
+    let (invidious, others): (Vec<_>, Vec<_>) = existing
+        .into_iter()
+        .zip(
+            tokens
+                .iter()
+                .map(Option::is_some)
+                .chain(std::iter::repeat(false)),
+        )
+        .partition(|(server, _)| server.api == ApiFlavour::Invidious);
+
+    let mut list = invidious
+        .into_iter()
+        .filter(|(server, logged_in)| {
+            *logged_in || !listed.iter().any(|url| is_same_url(url, &server.url))
+        })
+        .map(|(server, _)| server)
+        .collect::<Vec<_>>();
+    for url in working {
+        if !list.iter().any(|server| is_same_url(&server.url, &url)) {
+            list.push(config::Server {
+                url,
+                api: ApiFlavour::Invidious,
+            });
+        }
+    }
+    list.extend(others.into_iter().map(|(server, _)| server));
+    list
+}
+
+// Urls that answered trending with a list in time, fastest first. Every url is probed at once
+pub async fn probe(client: &reqwest::Client, urls: Vec<String>) -> Vec<String> {
+    let probes = urls
+        .into_iter()
+        .map(|url| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                let started = Instant::now();
+                let res = client
+                    .get(format!("{}{}", url, PROBE_PATH))
+                    .timeout(PROBE_TIMEOUT)
+                    .send()
+                    .await
+                    .and_then(|res| res.error_for_status());
+                let is_working = match res {
+                    Ok(res) => res
+                        .json::<serde_json::Value>()
+                        .await
+                        .is_ok_and(|trending| trending.is_array()),
+                    Err(_) => false,
+                };
+                is_working.then(|| (url, started.elapsed()))
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut working = Vec::new();
+    for probe in probes {
+        if let Ok(Some(res)) = probe.await {
+            working.push(res);
+        }
+    }
+    working.sort_by_key(|(_url, latency)| *latency);
+    working.into_iter().map(|(url, _latency)| url).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_healthy_https_instances_with_api() {
+        let body = br#"[
+            ["good.example", { "type": "https", "uri": "https://good.example", "api": true,
+                "monitor": { "30dRatio": { "ratio": "99.80" } } }],
+            ["slash.example", { "type": "https", "uri": "https://slash.example/", "api": true,
+                "monitor": { "30dRatio": { "ratio": "90.00" } } }],
+            ["flaky.example", { "type": "https", "uri": "https://flaky.example", "api": true,
+                "monitor": { "30dRatio": { "ratio": "62.15" } } }],
+            ["noapi.example", { "type": "https", "uri": "https://noapi.example", "api": false,
+                "monitor": { "30dRatio": { "ratio": "100.00" } } }],
+            ["unknown.example", { "type": "https", "uri": "https://unknown.example", "api": null,
+                "monitor": null }],
+            ["hidden.onion", { "type": "onion", "uri": "http://hidden.onion", "api": true,
+                "monitor": null }],
+            ["broken.example", { "type": "https" }]
+        ]"#;
+
+        assert_eq!(
+            candidates(body).unwrap(),
+            vec![
+                "https://good.example/api/v1".to_string(),
+                "https://slash.example/api/v1".to_string()
+            ]
+        );
+        assert!(candidates(b"<html>Down</html>").is_err());
+        // Entry that cannot be decoded is still skipped
+        assert_eq!(listed(body).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn keep_own_and_logged_in_servers() {
+        let server = |url: &str, api| config::Server {
+            url: url.to_string(),
+            api,
+        };
+        let existing = vec![
+            server("https://dead.example/api/v1", ApiFlavour::Invidious),
+            server("https://own.example/api/v1", ApiFlavour::Invidious),
+            server("https://pipedapi.example", ApiFlavour::Piped),
+            server("https://logged.example/api/v1/", ApiFlavour::Invidious),
+            server("https://good.example/api/v1", ApiFlavour::Invidious),
+        ];
+        let tokens = vec![None, None, None, Some("token".to_string()), None];
+        let listed = [
+            "https://dead.example/api/v1",
+            "https://logged.example/api/v1",
+            "https://good.example/api/v1",
+            "https://new.example/api/v1",
+        ]
+        .map(String::from);
+        let working = vec![
+            "https://new.example/api/v1".to_string(),
+            "https://good.example/api/v1".to_string(),
+        ];
+
+        let urls = merge(existing, &tokens, &listed, working)
+            .into_iter()
+            .map(|server| server.url)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            vec![
+                "https://own.example/api/v1",
+                "https://logged.example/api/v1/",
+                "https://new.example/api/v1",
+                "https://good.example/api/v1",
+                "https://pipedapi.example",
+            ]
+        );
+    }
+}
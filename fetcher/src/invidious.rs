@@ -0,0 +1,636 @@
+use crate::cache;
+use crate::captions::CaptionTrack;
+use crate::details::VideoDetails;
+use crate::lenient::{self, Lenient};
+use crate::releases::Release;
+use crate::search::SearchOptions;
+use crate::source::{Batch, Continuation, Endpoint, MusicSource, SourceFuture, TrendingCategory};
+use crate::{ArtistUnit, MusicUnit, PlaylistUnit, ReturnAction};
+use serde::Deserialize;
+
+const FIELDS: [&str; 3] = [
+    "videoId,title,author,lengthSeconds,liveNow",
+    "title,playlistId,author,videoCount",
+    "author,authorId,videoCount",
+];
+const FILTER_TYPE: [&str; 3] = ["music", "playlist", "channel"];
+
+// While fecthing playlist videos from endpoint /playlists/:plid
+// response is returned as "videos": [ { <Fields of MusicUnit> } ]
+// this structure is only used to convert such response to Vec<MusicUnit>
+#[derive(Deserialize, Clone, PartialEq)]
+struct FetchPlaylistContentRes {
+    #[serde(deserialize_with = "lenient::vec")]
+    videos: Vec<MusicUnit>,
+}
+
+// Response of /channels/:ucid/videos. Videos are returned in batches and `continuation` is
+// passed back to get the next batch. It is None after the last batch
+#[derive(Deserialize, Clone, PartialEq)]
+struct FetchChannelVideosRes {
+    #[serde(deserialize_with = "lenient::vec")]
+    videos: Vec<MusicUnit>,
+    continuation: Option<String>,
+}
+
+// Response of /channels/:ucid/videos when `published` is also asked for
+#[derive(Deserialize)]
+struct ChannelReleasesRes {
+    #[serde(deserialize_with = "lenient::vec")]
+    videos: Vec<Release>,
+}
+
+// Serve same purpose as described in struct FetchPlaylistContentRes but
+// to convert to Vec<PlaylistUnit>
+#[derive(Deserialize, Clone, PartialEq)]
+struct FetchArtistPlaylist {
+    #[serde(deserialize_with = "lenient::vec")]
+    playlists: Vec<PlaylistUnit>,
+}
+
+// Response of /videos/:id when only the related videos are asked for
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecommendedVideosRes {
+    #[serde(deserialize_with = "lenient::vec")]
+    recommended_videos: Vec<MusicUnit>,
+}
+
+// Response of /videos/:id with the fields needed for VideoDetails
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VideoDetailsRes {
+    video_id: String,
+    title: String,
+    author: String,
+    author_id: String,
+    #[serde(default)]
+    sub_count_text: String,
+    #[serde(default)]
+    view_count: u64,
+    #[serde(default)]
+    like_count: u64,
+    #[serde(default)]
+    published_text: String,
+    #[serde(default)]
+    genre: String,
+    #[serde(default)]
+    description: String,
+}
+
+impl From<VideoDetailsRes> for VideoDetails {
+    fn from(res: VideoDetailsRes) -> Self {
+        VideoDetails {
+            id: res.video_id,
+            title: res.title,
+            channel: res.author,
+            channel_id: res.author_id,
+            subscribers: res.sub_count_text,
+            views: res.view_count,
+            likes: res.like_count,
+            published: res.published_text,
+            genre: res.genre,
+            description: res.description,
+        }
+    }
+}
+
+// Response of /captions/:id
+#[derive(Deserialize)]
+struct CaptionsRes {
+    captions: Vec<CaptionRes>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CaptionRes {
+    label: String,
+    language_code: String,
+    url: String,
+}
+
+// Response of /search/suggestions
+#[derive(Deserialize)]
+struct SuggestionsRes {
+    suggestions: Vec<String>,
+}
+
+// Response of /auth/feed. `notifications` are the same kind of videos that are not yet seen and
+// are also part of `videos` so those are not used
+#[derive(Deserialize)]
+struct FeedRes {
+    #[serde(deserialize_with = "lenient::vec")]
+    videos: Vec<MusicUnit>,
+}
+
+// Single channel in response of /auth/subscriptions. Number of videos is not returned
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubscriptionRes {
+    author: String,
+    author_id: String,
+}
+
+// Response of POST /auth/playlists
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreatedPlaylistRes {
+    title: String,
+    playlist_id: String,
+}
+
+// Video of playlist in response of /auth/playlists/:plid. Same video can be in a playlist more
+// than once so video is removed by `indexId` which is unique within the playlist
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlaylistVideoIndexRes {
+    video_id: String,
+    index_id: String,
+}
+
+#[derive(Deserialize)]
+struct PlaylistIndexRes {
+    videos: Vec<PlaylistVideoIndexRes>,
+}
+
+// Source for servers powered by invidious. See: https://docs.invidious.io/api/
+// The unit types of this crate are deserialized directly from the response of invidious
+// so there is no conversion needed here
+pub struct Invidious;
+
+impl Invidious {
+    async fn search<Unit>(
+        endpoint: &Endpoint<'_>,
+        query: &str,
+        options: &SearchOptions,
+        from: Option<&Continuation>,
+        filter_index: usize,
+    ) -> Result<Batch<Unit>, crate::ReturnAction>
+    where
+        Unit: serde::de::DeserializeOwned + 'static,
+    {
+        // invidious paginates search by page number starting from 1
+        let page = match from {
+            Some(Continuation::Page(page)) => *page,
+            Some(Continuation::Token(_)) | None => 1,
+        };
+        // Every value is url encoded by the endpoint. Query itself may contain any character
+        let page_param = page.to_string();
+        let option_params = options.invidious_params();
+        let mut query_pairs: Vec<(&str, &str)> = vec![
+            ("q", query),
+            ("type", FILTER_TYPE[filter_index]),
+            ("region", endpoint.region),
+            ("page", &page_param),
+            ("fields", FIELDS[filter_index]),
+        ];
+        query_pairs.extend(
+            option_params
+                .iter()
+                .map(|(name, value)| (*name, value.as_str())),
+        );
+
+        let items = endpoint
+            .get_cached::<Lenient<Unit>>(cache::Kind::Search, "/search", &query_pairs)
+            .await?
+            .0;
+        // Invidious do not tell if there are more pages. Assume there is until empty page is returned
+        let next = if items.is_empty() {
+            None
+        } else {
+            Some(Continuation::Page(page + 1))
+        };
+        Ok(Batch { items, next })
+    }
+}
+
+impl MusicSource for Invidious {
+    fn search_music<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        query: &'a str,
+        options: &'a SearchOptions,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>> {
+        Box::pin(Self::search(endpoint, query, options, from, 0))
+    }
+
+    fn search_playlist<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        query: &'a str,
+        options: &'a SearchOptions,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<PlaylistUnit>> {
+        Box::pin(Self::search(endpoint, query, options, from, 1))
+    }
+
+    fn search_artist<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        query: &'a str,
+        options: &'a SearchOptions,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<ArtistUnit>> {
+        Box::pin(Self::search(endpoint, query, options, from, 2))
+    }
+
+    // Popular feed is same for every region
+    fn get_trending_music<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        category: TrendingCategory,
+    ) -> SourceFuture<'a, Vec<MusicUnit>> {
+        Box::pin(async move {
+            let trending_type = match category {
+                TrendingCategory::Music => Some("music"),
+                TrendingCategory::Gaming => Some("gaming"),
+                TrendingCategory::Movies => Some("movies"),
+                TrendingCategory::Default => None,
+                TrendingCategory::Popular => {
+                    return endpoint
+                        .get_cached::<Lenient<MusicUnit>>(
+                            cache::Kind::Trending,
+                            "/popular",
+                            &[("fields", FIELDS[0])],
+                        )
+                        .await
+                        .map(|res| res.0)
+                }
+            };
+
+            let mut query = vec![("region", endpoint.region), ("fields", FIELDS[0])];
+            if let Some(trending_type) = trending_type {
+                query.push(("type", trending_type));
+            }
+            endpoint
+                .get_cached::<Lenient<MusicUnit>>(cache::Kind::Trending, "/trending", &query)
+                .await
+                .map(|res| res.0)
+        })
+    }
+
+    fn get_playlist_content<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        playlist_id: &'a str,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>> {
+        Box::pin(async move {
+            // Playlist are paginated by page number starting from 1 same as search
+            let page = match from {
+                Some(Continuation::Page(page)) => *page,
+                Some(Continuation::Token(_)) | None => 1,
+            };
+            let path = format!("/playlists/{playlist_id}", playlist_id = playlist_id);
+            let fields = format!("videos({music_field})", music_field = FIELDS[0]);
+            let page_param = page.to_string();
+            let items = endpoint
+                .get_cached::<FetchPlaylistContentRes>(
+                    cache::Kind::Playlist,
+                    &path,
+                    &[("page", &page_param), ("fields", &fields)],
+                )
+                .await?
+                .videos;
+
+            let next = if items.is_empty() {
+                None
+            } else {
+                Some(Continuation::Page(page + 1))
+            };
+            Ok(Batch { items, next })
+        })
+    }
+
+    fn get_playlist_of_channel<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        channel_id: &'a str,
+    ) -> SourceFuture<'a, Vec<PlaylistUnit>> {
+        Box::pin(async move {
+            let path = format!("/channels/{channel_id}/playlists", channel_id = channel_id);
+            let fields = format!("playlists({channel_fields})", channel_fields = FIELDS[1]);
+            endpoint
+                .get_with_query::<FetchArtistPlaylist>(&path, &[("fields", &fields)])
+                .await
+                .map(|res| res.playlists)
+        })
+    }
+
+    fn get_videos_of_channel<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        channel_id: &'a str,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>> {
+        Box::pin(async move {
+            let path = format!("/channels/{channel_id}/videos", channel_id = channel_id);
+            let fields = format!(
+                "videos({music_field}),continuation",
+                music_field = FIELDS[0]
+            );
+            let mut query: Vec<(&str, &str)> = vec![("fields", &fields)];
+            if let Some(Continuation::Token(continuation)) = from {
+                query.push(("continuation", continuation));
+            }
+
+            let res = endpoint
+                .get_with_query::<FetchChannelVideosRes>(&path, &query)
+                .await?;
+            Ok(Batch {
+                items: res.videos,
+                next: res.continuation.map(Continuation::Token),
+            })
+        })
+    }
+
+    fn get_releases_of_channel<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        channel_id: &'a str,
+    ) -> SourceFuture<'a, Vec<Release>> {
+        Box::pin(async move {
+            let path = format!("/channels/{channel_id}/videos", channel_id = channel_id);
+            let fields = format!("videos({music_field},published)", music_field = FIELDS[0]);
+            endpoint
+                .get_with_query::<ChannelReleasesRes>(&path, &[("fields", &fields)])
+                .await
+                .map(|res| res.videos)
+        })
+    }
+
+    fn get_search_suggestions<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        query: &'a str,
+    ) -> SourceFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            endpoint
+                .get_with_query::<SuggestionsRes>("/search/suggestions", &[("q", query)])
+                .await
+                .map(|res| res.suggestions)
+        })
+    }
+
+    fn get_video_details<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+    ) -> SourceFuture<'a, VideoDetails> {
+        Box::pin(async move {
+            endpoint
+                .get_with_query::<VideoDetailsRes>(
+                    &format!("/videos/{music_id}", music_id = music_id),
+                    &[(
+                        "fields",
+                        "videoId,title,author,authorId,subCountText,viewCount,likeCount,\
+                        publishedText,genre,description",
+                    )],
+                )
+                .await
+                .map(VideoDetails::from)
+        })
+    }
+
+    fn get_related_music<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+    ) -> SourceFuture<'a, Vec<MusicUnit>> {
+        Box::pin(async move {
+            let fields = format!("recommendedVideos({music_field})", music_field = FIELDS[0]);
+            endpoint
+                .get_with_query::<RecommendedVideosRes>(
+                    &format!("/videos/{music_id}", music_id = music_id),
+                    &[("fields", &fields)],
+                )
+                .await
+                .map(|res| res.recommended_videos)
+        })
+    }
+
+    fn get_radio<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>> {
+        Box::pin(async move {
+            let seed = match from {
+                Some(Continuation::Token(seed)) => seed.as_str(),
+                Some(Continuation::Page(_)) | None => music_id,
+            };
+            let fields = format!("videos({music_field})", music_field = FIELDS[0]);
+            let mix = endpoint
+                .get_with_query::<FetchPlaylistContentRes>(
+                    &format!("/mixes/RD{seed}", seed = seed),
+                    &[("fields", &fields)],
+                )
+                .await;
+
+            // Not every video have a mix and some instances do not serve /mixes at all.
+            // Videos recommended alongside the seed are the next best thing
+            let items = match mix {
+                Ok(mix) => mix.videos,
+                Err(ReturnAction::Failed(_)) => self.get_related_music(endpoint, seed).await?,
+                Err(other) => return Err(other),
+            };
+
+            let next = items
+                .last()
+                .map(|music| Continuation::Token(music.id.clone()));
+            Ok(Batch { items, next })
+        })
+    }
+
+    fn get_caption_tracks<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+    ) -> SourceFuture<'a, Vec<CaptionTrack>> {
+        Box::pin(async move {
+            let res = endpoint
+                .get::<CaptionsRes>(&format!("/captions/{music_id}", music_id = music_id))
+                .await?;
+            let tracks = res
+                .captions
+                .into_iter()
+                .map(|caption| CaptionTrack {
+                    label: caption.label,
+                    language: caption.language_code,
+                    url: caption.url,
+                    api: Some(endpoint.api),
+                })
+                .collect();
+            Ok(tracks)
+        })
+    }
+
+    // Content of track is served from same endpoint with the label of track. The `url` in
+    // response is same thing but with `/api/v1` so it is not used here
+    fn get_captions<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+        track: &'a CaptionTrack,
+    ) -> SourceFuture<'a, String> {
+        Box::pin(async move {
+            endpoint
+                .get_text(
+                    &format!("/captions/{music_id}", music_id = music_id),
+                    &[("label", &track.label)],
+                )
+                .await
+        })
+    }
+
+    // Feed is paginated by page number starting from 1 same as search
+    fn get_feed<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>> {
+        Box::pin(async move {
+            let page = match from {
+                Some(Continuation::Page(page)) => *page,
+                Some(Continuation::Token(_)) | None => 1,
+            };
+            let page_param = page.to_string();
+            let items = endpoint
+                .get_authorized::<FeedRes>("/auth/feed", &[("page", &page_param)])
+                .await?
+                .videos;
+
+            let next = if items.is_empty() {
+                None
+            } else {
+                Some(Continuation::Page(page + 1))
+            };
+            Ok(Batch { items, next })
+        })
+    }
+
+    fn get_subscriptions<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+    ) -> SourceFuture<'a, Vec<ArtistUnit>> {
+        Box::pin(async move {
+            let subscriptions = endpoint
+                .get_authorized::<Lenient<SubscriptionRes>>("/auth/subscriptions", &[])
+                .await?;
+            Ok(subscriptions
+                .0
+                .into_iter()
+                .map(|channel| ArtistUnit {
+                    name: channel.author,
+                    id: channel.author_id,
+                    video_count: "-".to_string(),
+                })
+                .collect())
+        })
+    }
+
+    fn get_account_playlists<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+    ) -> SourceFuture<'a, Vec<PlaylistUnit>> {
+        Box::pin(async move {
+            endpoint
+                .get_authorized::<Lenient<PlaylistUnit>>("/auth/playlists", &[])
+                .await
+                .map(|res| res.0)
+        })
+    }
+
+    // Invidious returns at most 100 videos of the playlist here and have no way to ask for more
+    fn get_account_playlist_content<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        playlist_id: &'a str,
+    ) -> SourceFuture<'a, Vec<MusicUnit>> {
+        Box::pin(async move {
+            endpoint
+                .get_authorized::<FetchPlaylistContentRes>(
+                    &format!("/auth/playlists/{playlist_id}", playlist_id = playlist_id),
+                    &[],
+                )
+                .await
+                .map(|res| res.videos)
+        })
+    }
+
+    // New playlist is private. It can still be made public from the web interface
+    fn create_playlist<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        name: &'a str,
+    ) -> SourceFuture<'a, PlaylistUnit> {
+        Box::pin(async move {
+            let created = endpoint
+                .post_authorized::<CreatedPlaylistRes>(
+                    "/auth/playlists",
+                    &serde_json::json!({ "title": name, "privacy": "private" }),
+                )
+                .await?;
+            Ok(PlaylistUnit {
+                name: created.title,
+                id: created.playlist_id,
+                author: String::new(),
+                video_count: "0".to_string(),
+            })
+        })
+    }
+
+    fn add_to_playlist<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        playlist_id: &'a str,
+        music_id: &'a str,
+    ) -> SourceFuture<'a, ()> {
+        Box::pin(async move {
+            endpoint
+                .post_authorized::<serde_json::Value>(
+                    &format!(
+                        "/auth/playlists/{playlist_id}/videos",
+                        playlist_id = playlist_id
+                    ),
+                    &serde_json::json!({ "videoId": music_id }),
+                )
+                .await
+                .map(|_| ())
+        })
+    }
+
+    // Only the first occurrence of the music is removed if it is there more than once
+    fn remove_from_playlist<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        playlist_id: &'a str,
+        music_id: &'a str,
+    ) -> SourceFuture<'a, ()> {
+        Box::pin(async move {
+            let path = format!("/auth/playlists/{playlist_id}", playlist_id = playlist_id);
+            let playlist = endpoint
+                .get_authorized::<PlaylistIndexRes>(&path, &[])
+                .await?;
+            match playlist
+                .videos
+                .into_iter()
+                .find(|video| video.video_id == music_id)
+            {
+                Some(video) => {
+                    endpoint
+                        .delete_authorized(&format!(
+                            "{path}/videos/{index_id}",
+                            path = path,
+                            index_id = video.index_id
+                        ))
+                        .await
+                }
+                // Already removed. eg: from the web interface
+                None => Ok(()),
+            }
+        })
+    }
+}
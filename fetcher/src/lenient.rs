@@ -0,0 +1,179 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use serde_path_to_error::Segment;
+use std::cell::RefCell;
+
+// List of items in response that is decoded one item at a time. Servers sometimes return an
+// item or two that do not have all the fields or have field of unexpected type. eg: upcoming
+// premiere without `lengthSeconds`. Instead of failing the whole response because of them:
+// - field of unexpected type is dropped so that field with default value is still decoded
+// - item that still cannot be decoded is skipped and the rest are kept
+// What was wrong is kept by `collect` below so that it can be shown. Response that is not a list
+// at all still fails as usual
+// This is used as Res of the request when response itself is the list and with `vec` below
+// when list is in some field of the response
+#[derive(Debug, PartialEq)]
+pub struct Lenient<T>(pub Vec<T>);
+
+impl<'de, T> Deserialize<'de> for Lenient<T>
+where
+    T: DeserializeOwned,
+{
+    fn deserialize<D>(input: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let values: Vec<Value> = Deserialize::deserialize(input)?;
+        let items = values
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, value)| item(index, value))
+            .collect();
+        Ok(Lenient(items))
+    }
+}
+
+// Use as `#[serde(deserialize_with = "lenient::vec")]` on field that holds list of items
+pub fn vec<'de, D, T>(input: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    Lenient::deserialize(input).map(|lenient| lenient.0)
+}
+
+thread_local! {
+    // Problems of the response being decoded by collect() in this thread. None when nobody is
+    // collecting them and they are just dropped
+    static PROBLEMS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+// Run `decode` and also return every item that was repaired or skipped while doing so. Decoding
+// does not await so problems of other responses decoded in same thread never get mixed in
+pub fn collect<R>(decode: impl FnOnce() -> R) -> (R, Vec<String>) {
+    let outer = PROBLEMS.with(|problems| problems.replace(Some(Vec::new())));
+    let res = decode();
+    let problems = PROBLEMS.with(|problems| problems.replace(outer));
+    (res, problems.unwrap_or_default())
+}
+
+fn report(problem: String) {
+    PROBLEMS.with(|problems| {
+        if let Some(problems) = problems.borrow_mut().as_mut() {
+            problems.push(problem);
+        }
+    });
+}
+
+// Decode single item dropping the field in error until it either decodes or the error is not
+// about any particular field. eg: missing field
+fn item<T>(index: usize, mut value: Value) -> Option<T>
+where
+    T: DeserializeOwned,
+{
+    let kind = std::any::type_name::<T>();
+    let mut dropped: Vec<String> = Vec::new();
+
+    loop {
+        let error = match serde_path_to_error::deserialize::<_, T>(&value) {
+            Ok(item) => {
+                if !dropped.is_empty() {
+                    report(format!(
+                        "Dropped invalid field of {kind} at {index} in response. {fields}",
+                        kind = kind,
+                        index = index,
+                        fields = dropped.join(". ")
+                    ));
+                }
+                return Some(item);
+            }
+            Err(error) => error,
+        };
+
+        let field = match error.path().iter().next() {
+            Some(Segment::Map { key }) => key.clone(),
+            _ => String::new(),
+        };
+        let removed = value
+            .as_object_mut()
+            .and_then(|object| object.remove(&field));
+        if removed.is_none() {
+            dropped.push(format!(
+                "Error at `{path}`: {message}",
+                path = error.path(),
+                message = error.inner()
+            ));
+            report(format!(
+                "Skipped {kind} at {index} in response. {errors}",
+                kind = kind,
+                index = index,
+                errors = dropped.join(". ")
+            ));
+            return None;
+        }
+        dropped.push(format!(
+            "`{field}`: {message}",
+            field = field,
+            message = error.inner()
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MusicUnit, PlaylistUnit};
+    use std::time::Duration;
+
+    #[test]
+    fn keep_valid_and_repair_or_skip_invalid_items() {
+        let body = r#"[
+            { "videoId": "good", "title": "Good", "author": "A", "lengthSeconds": 60 },
+            { "videoId": "premiere", "title": "Premiere", "author": "A" },
+            { "videoId": "as_str", "title": "As string", "author": "A", "lengthSeconds": "215" },
+            { "videoId": "bad_live", "title": "Bad live", "author": "A", "lengthSeconds": 0, "liveNow": "yes" },
+            "not even an object"
+        ]"#;
+        let (music, problems) = collect(|| serde_json::from_str::<Lenient<MusicUnit>>(body));
+        let music = music.unwrap().0;
+
+        let ids = music
+            .iter()
+            .map(|music| music.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["good", "premiere", "as_str", "bad_live"]);
+        assert_eq!(music[1].duration, Duration::ZERO);
+        assert_eq!(music[2].duration, Duration::from_secs(215));
+        assert!(!music[3].live);
+        // `liveNow` of bad_live was dropped and the string was skipped
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("`liveNow`"));
+        assert!(problems[1].starts_with("Skipped"));
+
+        // Response that is not a list is still an error
+        assert!(serde_json::from_str::<Lenient<MusicUnit>>(r#"{"error": "down"}"#).is_err());
+    }
+
+    #[test]
+    fn lenient_field() {
+        #[derive(Deserialize)]
+        struct PlaylistsRes {
+            #[serde(deserialize_with = "vec")]
+            playlists: Vec<PlaylistUnit>,
+        }
+
+        let body = r#"{ "playlists": [
+            { "title": "Mix", "playlistId": "PL1", "author": "A", "videoCount": 12 },
+            { "title": "Broken", "playlistId": "PL2", "author": "A", "videoCount": null }
+        ]}"#;
+        let (res, problems) = collect(|| serde_json::from_str::<PlaylistsRes>(body));
+        let res = res.unwrap();
+        // null count is dropped and defaulted
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("`videoCount`"));
+        assert_eq!(res.playlists.len(), 2);
+        assert_eq!(res.playlists[0].video_count, "12");
+        assert_eq!(res.playlists[1].video_count, "");
+    }
+}
@@ -1,4 +1,18 @@
 use serde::{self, Deserialize, Serialize};
+pub mod account;
+pub mod cache;
+pub mod captions;
+pub mod details;
+pub mod fixtures;
+pub mod health;
+pub mod instances;
+pub mod invidious;
+pub mod lenient;
+pub mod paging;
+pub mod piped;
+pub mod releases;
+pub mod search;
+pub mod source;
 pub mod utils;
 use std::time::Duration;
 
@@ -7,42 +21,57 @@ pub trait ExtendDuration {
     fn from_string(inp: &str) -> Duration;
 }
 
+// Make the reqwest client connect through the proxy in Network section of config. Every client
+// should be built with this so that all of them honour the same proxy
+pub trait ExtendClientBuilder {
+    fn with_network(self, network: &config::Network) -> Self;
+}
+
+// Some servers send the number as string. eg: "lengthSeconds": "215"
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumOrStr {
+    Num(u64),
+    Str(String),
+}
+
+impl NumOrStr {
+    fn into_num<E: serde::de::Error>(self) -> Result<u64, E> {
+        match self {
+            NumOrStr::Num(num) => Ok(num),
+            NumOrStr::Str(num) => num
+                .trim()
+                .parse()
+                .map_err(|_| E::custom(format!("expected a number, found \"{}\"", num))),
+        }
+    }
+}
+
 fn num_to_str<'de, D>(input: D) -> Result<String, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    let num: usize = Deserialize::deserialize(input)?;
-    let mut res = num.to_string();
+    let num: NumOrStr = Deserialize::deserialize(input)?;
+    let mut res = num.into_num::<D::Error>()?.to_string();
     res.shrink_to_fit();
     Ok(res)
 }
 
-fn seconds_to_str<'de, D>(input: D) -> Result<String, D::Error>
+// Duration is sent as number of seconds by server. Live stream have the duration of 0
+fn seconds_to_duration<'de, D>(input: D) -> Result<Duration, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    // Note: If duration is set to 0:0 from the json response
-    // the the video may be live ({islive: true, ..} in response)
-    // to keep things simple ignore all those details and this will simply return "0:0"
-    // this should be documented to inform the user
-    let sec: u64 = Deserialize::deserialize(input)?;
-    let dur: Duration = Duration::from_secs(sec);
-    Ok(dur.to_string())
-}
-
-// While fecthing playlist videos from endpoint /playlists/:plid
-// response is returned as "videos": [ { <Fields of MusicUnit> } ]
-// this structure is only used to convert such response to Vec<MusicUnit>
-#[derive(Deserialize, Clone, PartialEq)]
-struct FetchPlaylistContentRes {
-    videos: Vec<MusicUnit>,
+    let sec: NumOrStr = Deserialize::deserialize(input)?;
+    Ok(Duration::from_secs(sec.into_num::<D::Error>()?))
 }
 
-// Serve same purpose as described in struct FetchPlaylistContentRes but
-// to convert to Vec<PlaylistUnit>
-#[derive(Deserialize, Clone, PartialEq)]
-struct FetchArtistPlaylist {
-    playlists: Vec<PlaylistUnit>,
+// Counterpart of seconds_to_duration so that serialized unit can be deserialized back
+fn duration_to_seconds<S>(duration: &Duration, output: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    output.serialize_u64(duration.as_secs())
 }
 
 // Represent the single playable music item.
@@ -59,11 +88,20 @@ pub struct MusicUnit {
     // server return this field as `title`
     #[serde(alias = "title")]
     pub name: String,
+    // Length of the music. This is zero for live stream, see `live`, and when server do not
+    // tell it. eg: upcoming premiere
     #[serde(alias = "lengthSeconds")]
-    #[serde(deserialize_with = "seconds_to_str")]
-    pub duration: String,
+    #[serde(default)]
+    #[serde(deserialize_with = "seconds_to_duration")]
+    #[serde(serialize_with = "duration_to_seconds")]
+    pub duration: Duration,
     #[serde(alias = "videoId")]
     pub id: String,
+    // true if this is a live stream. Live stream have no length and cannot be seeked
+    // server return this field as `liveNow`
+    #[serde(alias = "liveNow")]
+    #[serde(default)]
+    pub live: bool,
 }
 
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
@@ -83,29 +121,32 @@ pub struct PlaylistUnit {
     #[serde(alias = "playlistId")]
     pub id: String,
     pub author: String,
+    // Empty when server do not tell it. eg: `null` for mix
     #[serde(alias = "videoCount")]
+    #[serde(default)]
     #[serde(deserialize_with = "num_to_str")]
     pub video_count: String,
 }
 
 #[derive(Default)]
 struct SearchRes {
-    music: Vec<MusicUnit>,
-    playlist: Vec<PlaylistUnit>,
-    artist: Vec<ArtistUnit>,
+    music: paging::Paged<MusicUnit>,
+    playlist: paging::Paged<PlaylistUnit>,
+    artist: paging::Paged<ArtistUnit>,
     query: String,
-    last_fetched: i8,
+    // filters with which the query was searched. Same query with different options is new search
+    options: search::SearchOptions,
 }
 
 #[derive(Default)]
 struct ArtistRes {
-    music: (String, Vec<MusicUnit>),
+    music: (String, paging::Paged<MusicUnit>),
     playlist: (String, Vec<PlaylistUnit>),
 }
 
 #[derive(Default)]
 struct PlaylistRes {
-    music: Vec<MusicUnit>,
+    music: paging::Paged<MusicUnit>,
     id: String,
 }
 
@@ -118,7 +159,9 @@ pub enum ReturnAction {
     // This variat indicates that the fetch has failed and cannot be resolved on retrying
     // This may be due to several reasons including server down, network failure, parse failure
     // Also Failed is active when fetcher had retried and now had exceed the retry count
-    Failed,
+    // FetchError tells what actually went wrong. When request was tried on several servers this
+    // is the error from the last server
+    Failed(FetchError),
     // This variant simply indicates that the request has failed but doing the same request for
     // another time may suceed
     Retry,
@@ -132,49 +175,175 @@ pub enum ReturnAction {
     EOR,
 }
 
+// Reason of ReturnAction::Failed
+#[derive(Debug)]
+pub enum FetchError {
+    // Cannot connect to the server or connection broke before the response was read
+    Network {
+        server: String,
+        error: reqwest::Error,
+    },
+    // Server did not respond within the time limit
+    Timeout {
+        server: String,
+    },
+    // Server responded but not with a success status code
+    Status {
+        server: String,
+        status: reqwest::StatusCode,
+    },
+    // Response was not in the shape source expected it to be.
+    // path is the location inside the json where it failed. eg: `[3].lengthSeconds`
+    Decode {
+        server: String,
+        path: String,
+        message: String,
+    },
+    // Error while reading/writing the local storage. eg: favourates
+    Storage(rusqlite::Error),
+    // There was no server to send the request to. eg: empty server list in config
+    NoServer,
+    // Fetcher is replaying the recorded response but this request was never recorded.
+    // file is where the response was expected to be. See fixtures.rs
+    NotRecorded {
+        file: String,
+    },
+    // Request is on behalf of the account but no server have token of it. See account.rs
+    NoAccount,
+}
+
+impl FetchError {
+    // Few word description to be shown in status bar. See Display for the full message
+    pub fn short(&self) -> &'static str {
+        match self {
+            FetchError::Network { .. } => "Network error..",
+            FetchError::Timeout { .. } => "Server timeout..",
+            FetchError::Status { .. } => "Server error..",
+            FetchError::Decode { .. } => "Bad response..",
+            FetchError::Storage(_) => "Storage error..",
+            FetchError::NoServer => "No server..",
+            FetchError::NotRecorded { .. } => "Not recorded..",
+            FetchError::NoAccount => "Not logged in..",
+        }
+    }
+
+    // Whether the server itself is at fault so that it should be avoided for a while and the
+    // request sent to another server. Others (eg: 404 of a removed playlist, missing fixture)
+    // would be same in every server
+    pub fn is_server_fault(&self) -> bool {
+        match self {
+            FetchError::Network { .. } | FetchError::Timeout { .. } => true,
+            FetchError::Status { status, .. } => {
+                status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            _ => false,
+        }
+    }
+}
+
+// Message that also tells what the user can do about the error
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::Network { server, error } => write!(
+                f,
+                "Cannot connect to {}. ({}) Check your internet connection or remove this server from `Servers` in config.",
+                server, error
+            ),
+            FetchError::Timeout { server } => write!(
+                f,
+                "{} did not respond in time. Try again or increase `server_time_out` in config.",
+                server
+            ),
+            FetchError::Status { server, status } => write!(
+                f,
+                "{} responded with HTTP {}. The server may be down or rate limiting, it will be avoided for a while.",
+                server, status
+            ),
+            FetchError::Decode {
+                server,
+                path,
+                message,
+            } => write!(
+                f,
+                "Unexpected response from {} at `{}`: {}. The server may run an incompatible version, check its `api` in config.",
+                server, path, message
+            ),
+            FetchError::Storage(error) => write!(
+                f,
+                "Storage error: {}. Make sure storage.db3 in config directory is writable.",
+                error
+            ),
+            FetchError::NoServer => write!(
+                f,
+                "No server is available for this request. Add some servers to `Servers` in config."
+            ),
+            FetchError::NotRecorded { file } => write!(
+                f,
+                "No recorded response at {}. Record it first with `ytui_music run --record`.",
+                file
+            ),
+            FetchError::NoAccount => write!(
+                f,
+                "No server is logged in to an invidious account. Log in with `ytui_music login <server> <token>`."
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
 pub struct Fetcher {
-    // None if nothing of the trending music is selected.
-    // Stores the vector of music that is trending in music section in specified region
-    // trending_now, is never cleared in a session. Unlike many others data container
+    // Stores the vector of music that is trending in each category in current region. Category
+    // is absent until it is selected.
+    // trending_now, is only cleared when the region is changed. Unlike many others data container
     // below, this field is only appended and read. When user paginate and there are no more
     // result in container another web request method is made and result is again stored and never cleared.
     // This may bring little delay when user explore for first time in a session but after that everything
     // will be in memory making it smooth.
-    trending_now: Option<Vec<MusicUnit>>,
+    trending_now: std::collections::HashMap<source::TrendingCategory, Vec<MusicUnit>>,
 
     //playlist_content stores collection of music contained in a playlist
     // first field: (String) holds the unique if of playlist that is being read.
-    // All the content in given playlist id is fetched at once and stored meaning
-    // only single web request is needed and no other even when user asks for next page.
-    // On the other hand this means for a playlist with large amount of content
-    // takes more time to initilized.
-    // The needed request will return the array of music data. And currently there is no way
-    // to fetch only the necessary fields inside music struct. Which means even with playlist
-    // of samll size, over data is returend by the data that is just ignored from our side. Thus
-    // increasing the network traffic.
-    // On the positive side, it is not needed to send multiple request when user explore the content
-    // in pagiunation. Just the different chunks of data is returned. Also, this makes possible to
-    // feed the player backend with all the content of playlist which means playing from
-    // playlist don't have to get interrupted for fetching more data once played tha last item of
-    // last fetched data. Playback stops only when playlist ends.
+    // Content is fetched lazily in the batches server returns. Only as many batch as needed
+    // to fill the requested page are fetched so that huge playlist do not take forever to
+    // open. Fetched batches are kept until another playlist is opened so going back to
+    // previous page do not send any request. See paging.rs
     playlist_content: PlaylistRes,
 
     /*
     artist_content stores collection of music and also the collection of playlists
     from the channel
     First field: (String) holds the unique id of channel being fetched.
+    Uploads of channel are paged same as playlist_content. Playlists of channel is still
+    fetched at once.
     For more info see documentation on playlist_content above
     */
     artist_content: ArtistRes,
 
-    // List of available servers powered by invidious youtube data fetcher. All the servers should
-    // provide same endpoints to make request to and same pattern of return data. Which actually means
-    // all the servers must be powered by the same mahor version of invidious backend.
+    // Radio started from the music of given id (first field) and the music of it fetched so
+    // far. Radio is paged same as playlist_content except that it never ends
+    radio: (String, paging::Paged<MusicUnit>),
+
+    // Subscription feed of the account fetched so far. This is paged same as playlist_content
+    // but is dropped whenever the first page is asked again as new videos keep coming in
+    feed: paging::Paged<MusicUnit>,
+
+    // Latest uploads of favourate artists merged from all of them. See releases.rs
+    releases: releases::Fetched,
+
+    // List of available servers powered by invidious or piped youtube data fetcher. Each server
+    // carries the api it speaks and request is made through the source of that api. So servers
+    // of same api should be powered by the same major version of backend.
     // Most of the servers do not expect high amount of request to their api. So to protect this
     // it would be better to frequently change the server time to time even in single session.
     // To distribute the load between multiple servers it would be better if this list is kept growing
     // See the utils.rs file to see the format of server url.
-    servers: &'static [String],
+    // Request is sent to the healthiest server as recorded in `health` and when it fails, to the
+    // next healthiest one until request succeed or retry count is exceeded
+    // Fetcher itself only handles the pagination and keeping the fetched data, actual request
+    // and parsing the response is done by the source of the server's api. See source.rs
+    servers: Vec<config::Server>,
 
     // Container to store the result of search result.
     // First field: (String) is the query being searched for.
@@ -183,9 +352,19 @@ pub struct Fetcher {
     // The reqwest client itself. This is only initilized once per session.
     client: reqwest::Client,
 
+    // Health record of each server in servers[] in same order. This is loaded from storage when
+    // fetcher is initilized and saved back after every request. Fetchers built with
+    // FetcherBuilder::share_with() have the same records. See health.rs
+    health: health::Shared,
+
+    // Token of invidious account for each server in servers[] in same order. None if not logged
+    // in to that server. Request on behalf of the account is only sent to servers that have one.
+    // See account.rs
+    tokens: Vec<Option<String>>,
+
     // index that reference the servers[] field.
-    // When server need to be changes as described in documentation of servers[] field
-    // this index is updated (usually rotated clockwise)
+    // This is the server to which last request was made and is updated with the healthiest
+    // server before each request
     // TODO:
     // It may be more efficient to directly reference the elemnt from searvers[] rather than
     // storing the index and hence preventing accidintal out-of-index access
@@ -193,6 +372,46 @@ pub struct Fetcher {
 
     // copy of constants.item_per_list
     item_per_page: usize,
-    // reference to constants.region in config file
-    region: &'static str,
+    // constants.region in config file until another region is picked. See set_region()
+    region: String,
+
+    // Storage where favourates, server health, cached response and captions are kept. This is
+    // the global STORAGE for Fetcher::default() but can be any storage with the tables created
+    // by config::ConfigContainer::create_tables()
+    storage: config::initilize::Storage,
+    // How the response is cached in storage. copy of cache in config file
+    cache: config::Cache,
+    // Keys of stale response being refreshed in background. See cache::Refreshing
+    refreshing: cache::Refreshing,
+    // Whether the response is recorded to or replayed from files. See fixtures.rs
+    fixtures: config::Fixtures,
+    // Items that were repaired or skipped in the responses since last take_problems(). Only the
+    // last MAX_PROBLEMS are kept for fetcher whose problems are never taken
+    problems: Vec<String>,
+}
+
+/*
+Build the Fetcher from the values given explicitly instead of reading them from config file.
+Fetcher::default() is built with this from CONFIG and STORAGE. Anything that is not given is
+the default of config and storage is an in-memory database so that fetchers built this way
+never touch the files in config directory. This makes it possible to have several independent
+fetchers in same process. eg: in tests or a tool that talks with different set of servers
+
+    let fetcher = FetcherBuilder::default()
+        .servers(vec![server])
+        .region("NP")
+        .build();
+*/
+#[derive(Default)]
+pub struct FetcherBuilder {
+    servers: Option<Vec<config::Server>>,
+    region: Option<String>,
+    item_per_page: Option<usize>,
+    timeout: Option<Duration>,
+    storage: Option<config::initilize::Storage>,
+    cache: Option<config::Cache>,
+    fixtures: Option<config::Fixtures>,
+    network: Option<config::Network>,
+    health: Option<health::Shared>,
+    refreshing: Option<cache::Refreshing>,
 }
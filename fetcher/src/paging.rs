@@ -0,0 +1,252 @@
+use crate::source::{ApiFlavour, Batch, Continuation};
+
+// Batches in a row that had nothing new after which there is taken to be nothing more. See
+// extend_unique()
+const MAX_STALE_BATCHES: u8 = 3;
+
+// Result of a paginated request as fetched so far.
+// Front-end asks for local pages of `item_per_page` items while server returns batches of its
+// own size and tells where to continue from. These two are kept independent here: local page
+// is only an index into `items` and the server page is whatever `next` says. So one server
+// batch may fill several local pages or several batches may be needed for one local page.
+pub struct Paged<T> {
+    items: Vec<T>,
+    // Where to continue from and the api of server that returned it. Continuation is only
+    // meaningful to the same kind of api
+    next: Option<(ApiFlavour, Continuation)>,
+    // false until first batch is fetched. After that `next` being None means there is nothing
+    // more to fetch
+    started: bool,
+    // Batches in a row that were all already fetched. See extend_unique()
+    stale_batches: u8,
+}
+
+impl<T> Default for Paged<T> {
+    fn default() -> Self {
+        Paged {
+            items: Vec::new(),
+            next: None,
+            started: false,
+            stale_batches: 0,
+        }
+    }
+}
+
+impl<T> Paged<T> {
+    // Where to continue to fetch next batch. None for the first batch
+    pub fn next(&self) -> Option<&(ApiFlavour, Continuation)> {
+        self.next.as_ref()
+    }
+
+    // true if more batch should be fetched before `page` can be served in full
+    pub fn needs_more(&self, page: usize, item_per_page: usize) -> bool {
+        let can_continue = !self.started || self.next.is_some();
+        can_continue && self.items.len() < (page + 1) * item_per_page
+    }
+
+    // Append the batch fetched from the server of `api`
+    pub fn extend(&mut self, batch: Batch<T>, api: ApiFlavour) {
+        self.started = true;
+        // Server that keeps returning empty batch with continuation would make us loop forever
+        self.next = if batch.items.is_empty() {
+            None
+        } else {
+            batch.next.map(|next| (api, next))
+        };
+        self.items.extend(batch.items);
+    }
+
+    // Same as extend() but item that is already fetched is dropped. Whether there is more is
+    // decided from the batch as server returned it, so a batch that was all duplicate still
+    // continues unless that happens MAX_STALE_BATCHES times in a row. eg: mix that keeps
+    // returning the same music
+    pub fn extend_unique(&mut self, batch: Batch<T>, api: ApiFlavour)
+    where
+        T: PartialEq,
+    {
+        if batch.items.is_empty() {
+            return self.extend(batch, api);
+        }
+
+        let mut fresh: Vec<T> = Vec::with_capacity(batch.items.len());
+        for item in batch.items {
+            if !self.items.contains(&item) && !fresh.contains(&item) {
+                fresh.push(item);
+            }
+        }
+        self.stale_batches = if fresh.is_empty() {
+            self.stale_batches.saturating_add(1)
+        } else {
+            0
+        };
+
+        self.started = true;
+        self.next = if self.stale_batches >= MAX_STALE_BATCHES {
+            None
+        } else {
+            batch.next.map(|next| (api, next))
+        };
+        self.items.extend(fresh);
+    }
+
+    // Items of local `page`. Last page may have less than item_per_page items.
+    // None if there is nothing in that page
+    pub fn page(&self, page: usize, item_per_page: usize) -> Option<&[T]> {
+        let lower_limit = page * item_per_page;
+        let upper_limit = std::cmp::min(self.items.len(), lower_limit + item_per_page);
+        if lower_limit < upper_limit {
+            Some(&self.items[lower_limit..upper_limit])
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchOptions;
+    use crate::{FetcherBuilder, ReturnAction};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Fake invidious on localhost that have 3 search pages of 20 music each. Page asked in
+    // every request is pushed to `requested`. Returns the api url of it
+    async fn fake_invidious(requested: Arc<Mutex<Vec<usize>>>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/v1", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = vec![0; 4096];
+                let len = socket.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..len]);
+                let page = request
+                    .split(['?', '&', ' '])
+                    .find_map(|param| param.strip_prefix("page="))
+                    .and_then(|page| page.parse::<usize>().ok())
+                    .unwrap();
+                requested.lock().unwrap().push(page);
+
+                let music = if page <= 3 {
+                    (page - 1) * 20..page * 20
+                } else {
+                    0..0
+                };
+                let body = music
+                    .map(|id| {
+                        format!(
+                            r#"{{ "videoId": "{id}", "title": "{id}", "author": "A", "lengthSeconds": 60 }}"#,
+                            id = id
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",");
+                let body = format!("[{}]", body);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {len}\r\nConnection: close\r\n\r\n{body}",
+                    len = body.len(),
+                    body = body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        url
+    }
+
+    // Id of music in local `page` of search result. Real search! and fill_page! are used
+    async fn search(fetcher: &mut crate::Fetcher, page: usize) -> Result<Vec<usize>, ReturnAction> {
+        let music = fetcher
+            .search_music("query", &SearchOptions::default(), page)
+            .await?;
+        Ok(music
+            .into_iter()
+            .map(|music| music.id.parse::<usize>().unwrap())
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn server_page_is_independent_of_local_page() {
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let mut fetcher = FetcherBuilder::default()
+            .servers(vec![config::Server {
+                url: fake_invidious(Arc::clone(&requested)).await,
+                api: ApiFlavour::Invidious,
+            }])
+            .item_per_page(10)
+            .build();
+        // local page 0 and 1 are both in first server page
+        assert_eq!(
+            search(&mut fetcher, 0).await.unwrap(),
+            (0..10).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            search(&mut fetcher, 1).await.unwrap(),
+            (10..20).collect::<Vec<_>>()
+        );
+        assert_eq!(*requested.lock().unwrap(), vec![1]);
+
+        // jumping to local page 5 needs server page 2 and 3
+        assert_eq!(
+            search(&mut fetcher, 5).await.unwrap(),
+            (50..60).collect::<Vec<_>>()
+        );
+        assert_eq!(*requested.lock().unwrap(), vec![1, 2, 3]);
+
+        // server page 4 is empty so there is nothing more
+        assert!(matches!(
+            search(&mut fetcher, 6).await,
+            Err(ReturnAction::EOR)
+        ));
+        assert!(matches!(
+            search(&mut fetcher, 7).await,
+            Err(ReturnAction::EOR)
+        ));
+        assert_eq!(*requested.lock().unwrap(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn duplicate_batch_do_not_end_unique_result() {
+        let mut paged = Paged::default();
+        let batch = |items: Vec<usize>, page| Batch {
+            items,
+            next: Some(Continuation::Page(page)),
+        };
+
+        paged.extend_unique(batch(vec![1, 2, 2, 3], 2), ApiFlavour::Invidious);
+        paged.extend_unique(batch(vec![3, 1], 3), ApiFlavour::Invidious);
+        assert_eq!(
+            paged.next(),
+            Some(&(ApiFlavour::Invidious, Continuation::Page(3)))
+        );
+        paged.extend_unique(batch(vec![4, 2], 4), ApiFlavour::Invidious);
+        assert_eq!(paged.page(0, 10), Some(&[1, 2, 3, 4][..]));
+
+        // Server that only repeats itself is given up on
+        for page in 5..8 {
+            assert!(paged.next().is_some());
+            paged.extend_unique(batch(vec![1, 4], page), ApiFlavour::Invidious);
+        }
+        assert!(paged.next().is_none());
+        assert!(!paged.needs_more(1, 10));
+    }
+
+    #[test]
+    fn last_page_can_be_partial() {
+        let mut paged = Paged::default();
+        assert!(paged.needs_more(1, 10));
+        paged.extend(
+            Batch {
+                items: (0..15).collect(),
+                next: None,
+            },
+            ApiFlavour::Invidious,
+        );
+
+        assert!(!paged.needs_more(1, 10));
+        assert_eq!(paged.page(1, 10), Some(&(10..15).collect::<Vec<_>>()[..]));
+        assert!(!paged.needs_more(2, 10));
+        assert_eq!(paged.page(2, 10), None);
+    }
+}
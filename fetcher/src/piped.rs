@@ -0,0 +1,544 @@
+use crate::cache;
+use crate::captions::CaptionTrack;
+use crate::details::{self, VideoDetails};
+use crate::lenient::{self, Lenient};
+use crate::releases::Release;
+use crate::search::SearchOptions;
+use crate::source::{Batch, Continuation, Endpoint, MusicSource, SourceFuture, TrendingCategory};
+use crate::{ArtistUnit, MusicUnit, PlaylistUnit, ReturnAction};
+use serde::Deserialize;
+use std::time::Duration;
+
+// Value of `filter` query in /search endpoint for music/playlist/artist respectively
+const FILTER_TYPE: [&str; 3] = ["music_songs", "playlists", "channels"];
+
+// Piped returns every kind of item (stream, playlist or channel) with the link to it in `url`
+// field instead of the id. eg: "/watch?v=<id>", "/playlist?list=<id>" and "/channel/<id>"
+// This returns the id part of such url
+fn id_from_url(url: &str) -> String {
+    let id = url
+        .trim_start_matches("/watch?v=")
+        .trim_start_matches("/playlist?list=")
+        .trim_start_matches("/channel/");
+    id.to_string()
+}
+
+// piped returns -1 for the counts it do not know about
+fn count_to_str(count: i64) -> String {
+    if count < 0 {
+        "NaN".to_string()
+    } else {
+        count.to_string()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PipedStream {
+    url: String,
+    title: String,
+    uploader_name: Option<String>,
+    // -1 for live streams
+    #[serde(default)]
+    duration: i64,
+    // unix milliseconds. -1 when not known
+    #[serde(default)]
+    uploaded: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PipedPlaylist {
+    url: String,
+    name: String,
+    uploader_name: Option<String>,
+    #[serde(default)]
+    videos: i64,
+}
+
+#[derive(Deserialize)]
+struct PipedChannel {
+    url: String,
+    name: String,
+    #[serde(default)]
+    videos: i64,
+}
+
+// Response of /search and /nextpage/search
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "Item: serde::de::DeserializeOwned"))]
+struct PipedSearchRes<Item> {
+    #[serde(deserialize_with = "lenient::vec")]
+    items: Vec<Item>,
+    nextpage: Option<String>,
+}
+
+// Item in `relatedStreams` of /streams/:id. These may also be playlist which do not have
+// most of the fields of stream
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PipedRelated {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    title: Option<String>,
+    uploader_name: Option<String>,
+    #[serde(default)]
+    duration: i64,
+}
+
+// Response of /streams/:id when only related streams are of interest
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PipedRelatedRes {
+    #[serde(deserialize_with = "lenient::vec")]
+    related_streams: Vec<PipedRelated>,
+}
+
+// Response of /streams/:id with the fields needed for VideoDetails
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PipedStreamDetails {
+    title: String,
+    #[serde(default)]
+    uploader: String,
+    #[serde(default)]
+    uploader_url: String,
+    #[serde(default)]
+    uploader_subscriber_count: i64,
+    #[serde(default)]
+    views: i64,
+    #[serde(default)]
+    likes: i64,
+    #[serde(default)]
+    upload_date: String,
+    #[serde(default)]
+    category: String,
+    // in html
+    #[serde(default)]
+    description: String,
+}
+
+// Item in `subtitles` of /streams/:id
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PipedSubtitle {
+    url: String,
+    #[serde(default)]
+    mime_type: String,
+    name: String,
+    code: String,
+    #[serde(default)]
+    auto_generated: bool,
+}
+
+// Response of /streams/:id when only subtitles are of interest
+#[derive(Deserialize)]
+struct PipedSubtitlesRes {
+    subtitles: Vec<PipedSubtitle>,
+}
+
+// Response of /playlists/:id and /channel/:id as well as their /nextpage/ endpoint
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PipedStreamsRes {
+    #[serde(deserialize_with = "lenient::vec")]
+    related_streams: Vec<PipedStream>,
+    nextpage: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PipedChannelTab {
+    name: String,
+    data: String,
+}
+
+// Response of /channel/:id when only the tabs are of interest
+#[derive(Deserialize)]
+struct PipedChannelTabsRes {
+    #[serde(default)]
+    tabs: Vec<PipedChannelTab>,
+}
+
+// Response of /channels/tabs
+#[derive(Deserialize)]
+struct PipedTabContentRes {
+    #[serde(deserialize_with = "lenient::vec")]
+    content: Vec<PipedPlaylist>,
+}
+
+impl From<PipedStream> for MusicUnit {
+    fn from(stream: PipedStream) -> Self {
+        MusicUnit {
+            artist: stream.uploader_name.unwrap_or_default(),
+            name: stream.title,
+            duration: Duration::from_secs(stream.duration.max(0) as u64),
+            id: id_from_url(&stream.url),
+            live: stream.duration < 0,
+        }
+    }
+}
+
+impl From<PipedPlaylist> for PlaylistUnit {
+    fn from(playlist: PipedPlaylist) -> Self {
+        PlaylistUnit {
+            name: playlist.name,
+            id: id_from_url(&playlist.url),
+            author: playlist.uploader_name.unwrap_or_default(),
+            video_count: count_to_str(playlist.videos),
+        }
+    }
+}
+
+impl From<PipedChannel> for ArtistUnit {
+    fn from(channel: PipedChannel) -> Self {
+        ArtistUnit {
+            name: channel.name,
+            id: id_from_url(&channel.url),
+            video_count: count_to_str(channel.videos),
+        }
+    }
+}
+
+// Source for servers powered by piped. See: https://docs.piped.video/docs/api-documentation/
+// Response of piped are in different shape than of the units in this crate so they are first
+// deserialized to Piped* structs above and then converted.
+// Piped do not have page numbers and instead every paginated response contains `nextpage`
+// token which should be passed to /nextpage/<same endpoint> to get the next batch
+pub struct Piped;
+
+impl Piped {
+    async fn search<Item, Unit>(
+        endpoint: &Endpoint<'_>,
+        query: &str,
+        from: Option<&Continuation>,
+        filter_index: usize,
+    ) -> Result<Batch<Unit>, ReturnAction>
+    where
+        Item: serde::de::DeserializeOwned + Into<Unit> + 'static,
+    {
+        let filter = FILTER_TYPE[filter_index];
+        let res = match from {
+            Some(Continuation::Token(nextpage)) => {
+                endpoint
+                    .get_cached::<PipedSearchRes<Item>>(
+                        cache::Kind::Search,
+                        "/nextpage/search",
+                        &[("q", query), ("filter", filter), ("nextpage", nextpage)],
+                    )
+                    .await?
+            }
+            Some(Continuation::Page(_)) | None => {
+                endpoint
+                    .get_cached::<PipedSearchRes<Item>>(
+                        cache::Kind::Search,
+                        "/search",
+                        &[("q", query), ("filter", filter)],
+                    )
+                    .await?
+            }
+        };
+
+        Ok(Batch {
+            items: res.items.into_iter().map(Into::into).collect(),
+            next: res.nextpage.map(Continuation::Token),
+        })
+    }
+
+    // Fetch one batch of streams. First batch is from `path` itself and later ones are from
+    // /nextpage`path` following the `nextpage` token. `path` is /playlists/:id or /channel/:id
+    // Response is cached as `kind` if given
+    async fn streams(
+        endpoint: &Endpoint<'_>,
+        path: &str,
+        from: Option<&Continuation>,
+        kind: Option<cache::Kind>,
+    ) -> Result<Batch<MusicUnit>, ReturnAction> {
+        let (path, query) = match from {
+            Some(Continuation::Token(nextpage)) => (
+                format!("/nextpage{}", path),
+                vec![("nextpage", nextpage.as_str())],
+            ),
+            Some(Continuation::Page(_)) | None => (path.to_string(), Vec::new()),
+        };
+        let res = match kind {
+            Some(kind) => {
+                endpoint
+                    .get_cached::<PipedStreamsRes>(kind, &path, &query)
+                    .await?
+            }
+            None => {
+                endpoint
+                    .get_with_query::<PipedStreamsRes>(&path, &query)
+                    .await?
+            }
+        };
+
+        Ok(Batch {
+            items: res
+                .related_streams
+                .into_iter()
+                .map(MusicUnit::from)
+                .collect(),
+            next: res.nextpage.map(Continuation::Token),
+        })
+    }
+}
+
+impl MusicSource for Piped {
+    fn search_music<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        query: &'a str,
+        // piped do not have any of these filters
+        _options: &'a SearchOptions,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>> {
+        Box::pin(Self::search::<PipedStream, MusicUnit>(
+            endpoint, query, from, 0,
+        ))
+    }
+
+    fn search_playlist<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        query: &'a str,
+        // piped do not have any of these filters
+        _options: &'a SearchOptions,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<PlaylistUnit>> {
+        Box::pin(Self::search::<PipedPlaylist, PlaylistUnit>(
+            endpoint, query, from, 1,
+        ))
+    }
+
+    fn search_artist<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        query: &'a str,
+        // piped do not have any of these filters
+        _options: &'a SearchOptions,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<ArtistUnit>> {
+        Box::pin(Self::search::<PipedChannel, ArtistUnit>(
+            endpoint, query, from, 2,
+        ))
+    }
+
+    fn get_trending_music<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        _category: TrendingCategory,
+    ) -> SourceFuture<'a, Vec<MusicUnit>> {
+        Box::pin(async move {
+            // piped do not have category in trending nor the popular feed. This is the trending
+            // of all type
+            let res = endpoint
+                .get_cached::<Lenient<PipedStream>>(
+                    cache::Kind::Trending,
+                    "/trending",
+                    &[("region", endpoint.region)],
+                )
+                .await?;
+            Ok(res.0.into_iter().map(MusicUnit::from).collect())
+        })
+    }
+
+    fn get_playlist_content<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        playlist_id: &'a str,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>> {
+        Box::pin(async move {
+            let path = format!("/playlists/{}", playlist_id);
+            Self::streams(endpoint, &path, from, Some(cache::Kind::Playlist)).await
+        })
+    }
+
+    fn get_playlist_of_channel<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        channel_id: &'a str,
+    ) -> SourceFuture<'a, Vec<PlaylistUnit>> {
+        Box::pin(async move {
+            // Playlists are not part of /channel/:id response itself. Instead it contains the
+            // list of tabs and the data of playlist tab should be passed to /channels/tabs
+            let channel = endpoint
+                .get::<PipedChannelTabsRes>(&format!("/channel/{}", channel_id))
+                .await?;
+            let playlist_tab = channel.tabs.into_iter().find(|tab| tab.name == "playlists");
+
+            match playlist_tab {
+                Some(tab) => {
+                    let res = endpoint
+                        .get_with_query::<PipedTabContentRes>(
+                            "/channels/tabs",
+                            &[("data", &tab.data)],
+                        )
+                        .await?;
+                    Ok(res.content.into_iter().map(PlaylistUnit::from).collect())
+                }
+                // channel do not have any playlist
+                None => Ok(Vec::new()),
+            }
+        })
+    }
+
+    fn get_videos_of_channel<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        channel_id: &'a str,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>> {
+        Box::pin(async move {
+            let path = format!("/channel/{}", channel_id);
+            Self::streams(endpoint, &path, from, None).await
+        })
+    }
+
+    fn get_releases_of_channel<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        channel_id: &'a str,
+    ) -> SourceFuture<'a, Vec<Release>> {
+        Box::pin(async move {
+            let res = endpoint
+                .get::<PipedStreamsRes>(&format!("/channel/{}", channel_id))
+                .await?;
+
+            Ok(res
+                .related_streams
+                .into_iter()
+                .map(|stream| Release {
+                    published: stream.uploaded.max(0) as u64 / 1000,
+                    music: MusicUnit::from(stream),
+                })
+                .collect())
+        })
+    }
+
+    fn get_search_suggestions<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        query: &'a str,
+    ) -> SourceFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            // piped returns just the array of suggestions
+            endpoint
+                .get_with_query::<Vec<String>>("/suggestions", &[("query", query)])
+                .await
+        })
+    }
+
+    fn get_video_details<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+    ) -> SourceFuture<'a, VideoDetails> {
+        Box::pin(async move {
+            let res = endpoint
+                .get::<PipedStreamDetails>(&format!("/streams/{}", music_id))
+                .await?;
+
+            Ok(VideoDetails {
+                id: music_id.to_string(),
+                title: res.title,
+                channel: res.uploader,
+                channel_id: id_from_url(&res.uploader_url),
+                subscribers: count_to_str(res.uploader_subscriber_count),
+                views: res.views.max(0) as u64,
+                likes: res.likes.max(0) as u64,
+                published: res.upload_date,
+                genre: res.category,
+                description: details::html_to_text(&res.description),
+            })
+        })
+    }
+
+    fn get_related_music<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+    ) -> SourceFuture<'a, Vec<MusicUnit>> {
+        Box::pin(async move {
+            let res = endpoint
+                .get::<PipedRelatedRes>(&format!("/streams/{}", music_id))
+                .await?;
+
+            Ok(res
+                .related_streams
+                .into_iter()
+                .filter(|related| related.kind == "stream")
+                .map(|related| {
+                    MusicUnit::from(PipedStream {
+                        url: related.url,
+                        title: related.title.unwrap_or_default(),
+                        uploader_name: related.uploader_name,
+                        duration: related.duration,
+                        uploaded: -1,
+                    })
+                })
+                .collect())
+        })
+    }
+
+    fn get_radio<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>> {
+        Box::pin(async move {
+            // Piped do not have mixes so related streams of the seed is used instead
+            let seed = match from {
+                Some(Continuation::Token(seed)) => seed.as_str(),
+                Some(Continuation::Page(_)) | None => music_id,
+            };
+            let items = self.get_related_music(endpoint, seed).await?;
+            let next = items
+                .last()
+                .map(|music| Continuation::Token(music.id.clone()));
+            Ok(Batch { items, next })
+        })
+    }
+
+    // Subtitles may also be in ttml. Only WebVTT ones are returned
+    fn get_caption_tracks<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+    ) -> SourceFuture<'a, Vec<CaptionTrack>> {
+        Box::pin(async move {
+            let res = endpoint
+                .get::<PipedSubtitlesRes>(&format!("/streams/{}", music_id))
+                .await?;
+
+            Ok(res
+                .subtitles
+                .into_iter()
+                .filter(|subtitle| subtitle.mime_type.contains("vtt"))
+                .map(|subtitle| CaptionTrack {
+                    label: if subtitle.auto_generated {
+                        format!("{} (auto-generated)", subtitle.name)
+                    } else {
+                        subtitle.name
+                    },
+                    language: subtitle.code,
+                    url: subtitle.url,
+                    api: Some(endpoint.api),
+                })
+                .collect())
+        })
+    }
+
+    // `url` of subtitle is full link to piped proxy
+    fn get_captions<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        _music_id: &'a str,
+        track: &'a CaptionTrack,
+    ) -> SourceFuture<'a, String> {
+        Box::pin(async move { endpoint.get_text_from(&track.url).await })
+    }
+}
@@ -0,0 +1,238 @@
+use crate::source::{Endpoint, MusicSource};
+use crate::{MusicUnit, ReturnAction};
+use config::initilize::{Storage, TB_FAVOURATES_ARTIST, TB_SEEN_RELEASE};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+// Uploads of this many artists are fetched at once. Rest wait for one of them to complete so
+// that following hundreds of artists do not flood the server with hundreds of request
+const CONCURRENCY: usize = 4;
+// Only this many latest uploads of each artist are shown. Otherwise an artist who uploads
+// everyday would push out everyone else
+const PER_ARTIST: usize = 10;
+// Fetched releases are shown again for this long. eg: when going back to the first page
+const FRESH_FOR: Duration = Duration::from_secs(5 * 60);
+// Music seen longer than this ago is forgotten. By then it is not the latest upload of its
+// artist anymore and is never fetched again
+const SEEN_KEPT_FOR_SECS: u64 = 60 * 60 * 24 * 180;
+
+// Music uploaded by an artist along with when it was published in unix seconds. Server returns
+// the fields of MusicUnit and `published` in the same object
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct Release {
+    #[serde(flatten)]
+    pub music: MusicUnit,
+    // 0 when server do not tell
+    #[serde(default)]
+    pub published: u64,
+}
+
+// Merged releases kept by Fetcher so that other pages are served without fetching again.
+// These are of the `artists` that were followed when fetching
+#[derive(Default)]
+pub struct Fetched {
+    pub artists: Vec<String>,
+    pub at: Option<Instant>,
+    pub music: Vec<MusicUnit>,
+}
+
+impl Fetched {
+    // Artist that was followed or unfollowed meanwhile should show up or go away right away
+    pub fn is_fresh_for(&self, artists: &[String]) -> bool {
+        self.artists == artists && self.at.is_some_and(|at| at.elapsed() < FRESH_FOR)
+    }
+}
+
+// Latest PER_ARTIST uploads of each artist, newest first among all of them. Music uploaded in
+// collaboration is listed by every artist involved so it is only kept once
+pub fn merge(per_artist: Vec<Vec<Release>>) -> Vec<MusicUnit> {
+    let mut releases = per_artist
+        .into_iter()
+        .flat_map(|mut releases| {
+            releases.sort_by_key(|release| std::cmp::Reverse(release.published));
+            releases.truncate(PER_ARTIST);
+            releases
+        })
+        .collect::<Vec<Release>>();
+    // Stable sort so that uploads of same time keep the order of artists
+    releases.sort_by_key(|release| std::cmp::Reverse(release.published));
+
+    let mut ids = HashSet::new();
+    releases
+        .into_iter()
+        .filter(|release| ids.insert(release.music.id.clone()))
+        .map(|release| release.music)
+        .collect()
+}
+
+// Recent uploads of every artist in `artists` from the server of `endpoint` merged together.
+// Artist whose uploads cannot be fetched is left out so that one deleted channel do not hide
+// everything. Fails only when none of them could be fetched
+pub async fn fetch_all(
+    source: &dyn MusicSource,
+    endpoint: &Endpoint<'_>,
+    artists: &[String],
+) -> Result<Vec<MusicUnit>, ReturnAction> {
+    let mut waiting = artists.iter();
+    let mut running = Vec::with_capacity(CONCURRENCY);
+    let mut fetched = Vec::with_capacity(artists.len());
+    let mut last_error = None;
+
+    loop {
+        while running.len() < CONCURRENCY {
+            match waiting.next() {
+                Some(artist_id) => {
+                    running.push(source.get_releases_of_channel(endpoint, artist_id))
+                }
+                None => break,
+            }
+        }
+        if running.is_empty() {
+            break;
+        }
+
+        // Whichever of the running request completes first
+        let res = std::future::poll_fn(|cx| {
+            let mut done = None;
+            let index = running.iter_mut().position(|request| {
+                done = match request.as_mut().poll(cx) {
+                    Poll::Ready(res) => Some(res),
+                    Poll::Pending => None,
+                };
+                done.is_some()
+            });
+            match (index, done) {
+                (Some(index), Some(res)) => {
+                    // Completed request is of no use anymore
+                    drop(running.swap_remove(index));
+                    Poll::Ready(res)
+                }
+                _ => Poll::Pending,
+            }
+        })
+        .await;
+
+        match res {
+            Ok(releases) => fetched.push(releases),
+            Err(err) => last_error = Some(err),
+        }
+    }
+
+    match last_error {
+        Some(err) if fetched.is_empty() => Err(err),
+        _ => Ok(merge(fetched)),
+    }
+}
+
+// Id of every artist in favourates
+pub fn followed(storage: &Storage) -> rusqlite::Result<Vec<String>> {
+    let query = format!("SELECT id FROM {tb_name}", tb_name = TB_FAVOURATES_ARTIST);
+    let conn = storage.lock().unwrap();
+    let mut stmt = conn.prepare(&query)?;
+    let artists = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>();
+    artists
+}
+
+// Id of every music that was ever shown in new releases
+pub fn seen(storage: &Storage) -> HashSet<String> {
+    let query = format!("SELECT id FROM {tb_name}", tb_name = TB_SEEN_RELEASE);
+    let conn = storage.lock().unwrap();
+    let seen = conn.prepare(&query).and_then(|mut stmt| {
+        stmt.query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<HashSet<String>>>()
+    });
+    seen.unwrap_or_default()
+}
+
+// Remember the music as seen. Time it was first seen is kept so that it can be forgotten later
+pub fn mark_seen(storage: &Storage, music: &[MusicUnit], now: u64) {
+    let insert = format!(
+        "INSERT OR IGNORE INTO {tb_name} (id, seen_at) VALUES (?1, ?2)",
+        tb_name = TB_SEEN_RELEASE
+    );
+    let forget = format!(
+        "DELETE FROM {tb_name} WHERE seen_at < ?1",
+        tb_name = TB_SEEN_RELEASE
+    );
+
+    let conn = storage.lock().unwrap();
+    let res = conn.prepare(&insert).and_then(|mut stmt| {
+        for music in music {
+            stmt.execute(rusqlite::params![music.id, now])?;
+        }
+        conn.execute(&forget, [now.saturating_sub(SEEN_KEPT_FOR_SECS)])
+    });
+    if let Err(err) = res {
+        eprintln!("Cannot save seen releases. Error: {err}", err = err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn release(id: &str, published: u64) -> Release {
+        Release {
+            music: MusicUnit {
+                artist: String::new(),
+                name: id.to_string(),
+                duration: Duration::from_secs(1),
+                id: id.to_string(),
+                live: false,
+            },
+            published,
+        }
+    }
+
+    #[test]
+    fn merge_newest_first_without_duplicate() {
+        let mut prolific = (0..15)
+            .map(|i| release(&format!("p{}", i), 100 + i))
+            .collect::<Vec<_>>();
+        prolific.reverse();
+        let merged = merge(vec![
+            prolific,
+            vec![release("old", 1), release("collab", 500)],
+            vec![release("collab", 500), release("new", 1000)],
+        ]);
+        let ids = merged
+            .iter()
+            .map(|music| music.id.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            ids,
+            vec![
+                "new", "collab", "p14", "p13", "p12", "p11", "p10", "p9", "p8", "p7", "p6", "p5",
+                "old"
+            ]
+        );
+    }
+
+    #[test]
+    fn seen_is_remembered_until_it_is_old() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        config::ConfigContainer::create_tables(&conn).unwrap();
+        let storage: Storage = Arc::new(Mutex::new(conn));
+
+        mark_seen(&storage, &[release("first", 0).music], 1000);
+        mark_seen(
+            &storage,
+            &[release("first", 0).music, release("second", 0).music],
+            2000,
+        );
+        assert_eq!(
+            seen(&storage),
+            HashSet::from(["first".to_string(), "second".to_string()])
+        );
+
+        // first was seen at 1000 and is not refreshed by seeing it again
+        mark_seen(&storage, &[], 1001 + SEEN_KEPT_FOR_SECS);
+        assert_eq!(seen(&storage), HashSet::from(["second".to_string()]));
+    }
+}
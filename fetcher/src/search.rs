@@ -0,0 +1,171 @@
+// Define an enum of search filter values along with the name of each value as typed in searchbar
+// and the value sent to invidious
+macro_rules! search_filter {
+    ($name: ident { $($variant: ident => $inline: literal, $param: literal),+ $(,)? }) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum $name {
+            $($variant),+
+        }
+
+        impl $name {
+            pub fn from_inline(value: &str) -> Option<Self> {
+                match value {
+                    $($inline => Some($name::$variant),)+
+                    _ => None,
+                }
+            }
+
+            pub fn as_param(self) -> &'static str {
+                match self {
+                    $($name::$variant => $param),+
+                }
+            }
+        }
+    };
+}
+
+search_filter!(SortBy {
+    Relevance => "relevance", "relevance",
+    Rating => "rating", "rating",
+    UploadDate => "date", "upload_date",
+    ViewCount => "views", "view_count",
+});
+
+search_filter!(UploadDate {
+    Hour => "hour", "hour",
+    Today => "today", "today",
+    Week => "week", "week",
+    Month => "month", "month",
+    Year => "year", "year",
+});
+
+search_filter!(Length {
+    Short => "short", "short",
+    Medium => "medium", "medium",
+    Long => "long", "long",
+});
+
+search_filter!(Feature {
+    Hd => "hd", "hd",
+    Subtitles => "subtitles", "subtitles",
+    CreativeCommons => "cc", "creative_commons",
+    ThreeD => "3d", "3d",
+    Live => "live", "live",
+    Purchased => "purchased", "purchased",
+    FourK => "4k", "4k",
+    ThreeSixty => "360", "360",
+    Location => "location", "location",
+    Hdr => "hdr", "hdr",
+    Vr180 => "vr180", "vr180",
+});
+
+// Filters to narrow down the search result. None (or empty) means no filter of that kind.
+// Only invidious supports these filters. Piped source ignores them
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub sort_by: Option<SortBy>,
+    pub date: Option<UploadDate>,
+    pub duration: Option<Length>,
+    pub features: Vec<Feature>,
+}
+
+impl SearchOptions {
+    // Split the query typed in searchbar to actual query and the filters in it.
+    // Filters are written as `key:value` anywhere in the query. eg: `lofi dur:long sort:views`
+    //   sort: relevance, rating, date, views
+    //   date: hour, today, week, month, year
+    //   dur:  short, medium, long
+    //   feat: hd, subtitles, cc, 3d, live, purchased, 4k, 360, location, hdr, vr180
+    //         multiple features are seperated by comma. eg: `feat:hd,cc`
+    // Word that looks like filter but with unknown key or value is kept in the query as is
+    pub fn parse(input: &str) -> (String, SearchOptions) {
+        let mut options = SearchOptions::default();
+        let mut query: Vec<&str> = Vec::new();
+
+        for word in input.split_whitespace() {
+            let is_filter = match word.split_once(':') {
+                Some(("sort", value)) => SortBy::from_inline(value)
+                    .map(|sort_by| options.sort_by = Some(sort_by))
+                    .is_some(),
+                Some(("date", value)) => UploadDate::from_inline(value)
+                    .map(|date| options.date = Some(date))
+                    .is_some(),
+                Some(("dur", value)) => Length::from_inline(value)
+                    .map(|duration| options.duration = Some(duration))
+                    .is_some(),
+                Some(("feat", value)) => value
+                    .split(',')
+                    .map(Feature::from_inline)
+                    .collect::<Option<Vec<Feature>>>()
+                    .map(|features| {
+                        for feature in features {
+                            if !options.features.contains(&feature) {
+                                options.features.push(feature);
+                            }
+                        }
+                    })
+                    .is_some(),
+                _ => false,
+            };
+
+            if !is_filter {
+                query.push(word);
+            }
+        }
+
+        (query.join(" "), options)
+    }
+
+    // Query parameters to send to invidious /search for these options
+    pub fn invidious_params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(sort_by) = self.sort_by {
+            params.push(("sort_by", sort_by.as_param().to_string()));
+        }
+        if let Some(date) = self.date {
+            params.push(("date", date.as_param().to_string()));
+        }
+        if let Some(duration) = self.duration {
+            params.push(("duration", duration.as_param().to_string()));
+        }
+        if !self.features.is_empty() {
+            let features: Vec<&str> = self.features.iter().map(|f| f.as_param()).collect();
+            params.push(("features", features.join(",")));
+        }
+        params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_inline_filters() {
+        let (query, options) = SearchOptions::parse("lofi dur:long  beats sort:views feat:hd,cc");
+        assert_eq!(query, "lofi beats");
+        assert_eq!(
+            options,
+            SearchOptions {
+                sort_by: Some(SortBy::ViewCount),
+                date: None,
+                duration: Some(Length::Long),
+                features: vec![Feature::Hd, Feature::CreativeCommons],
+            }
+        );
+        assert_eq!(
+            options.invidious_params(),
+            vec![
+                ("sort_by", "view_count".to_string()),
+                ("duration", "long".to_string()),
+                ("features", "hd,creative_commons".to_string()),
+            ]
+        );
+
+        // unknown key or value is part of query
+        let (query, options) = SearchOptions::parse("re:zero dur:forever feat:hd,nope");
+        assert_eq!(query, "re:zero dur:forever feat:hd,nope");
+        assert_eq!(options, SearchOptions::default());
+        assert!(options.invidious_params().is_empty());
+    }
+}
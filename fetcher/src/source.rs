@@ -0,0 +1,568 @@
+use crate::captions::CaptionTrack;
+use crate::details::VideoDetails;
+use crate::releases::Release;
+use crate::search::SearchOptions;
+use crate::{cache, fixtures, health, invidious::Invidious, lenient, piped::Piped};
+use crate::{ArtistUnit, FetchError, MusicUnit, PlaylistUnit, ReturnAction};
+use config::initilize::Storage;
+pub use config::ApiFlavour;
+use config::FixtureMode;
+use reqwest::Method;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+// Future returned by every method of MusicSource. Methods of a trait cannot be `async` and still
+// be used as `dyn MusicSource` so the future is boxed by hand instead.
+// 'a is the lifetime of the source and the endpoint the request is made from
+pub type SourceFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, ReturnAction>> + Send + 'a>>;
+
+// Everything a source needs to make a single request. This is built by the Fetcher for every
+// request so that the source do not have to care about which server is being used or how the
+// servers are rotated.
+pub struct Endpoint<'a> {
+    // The reqwest client of the Fetcher. Shared by all the sources
+    pub client: &'a reqwest::Client,
+    // Base url of the server to which this request should be sent. eg: https://vid.puffyan.us/api/v1
+    pub server: &'a str,
+    // region as set in config or picked in the tui. Used in trending and search
+    pub region: &'a str,
+    // api of the server. Cached response are only reused among servers of same api
+    pub api: ApiFlavour,
+    // Storage of the Fetcher where the response is cached and the cache settings to do so with
+    pub storage: &'a Storage,
+    pub cache: &'a config::Cache,
+    // Keys of stale cached response being refetched in background. See cache::Refreshing
+    pub refreshing: &'a cache::Refreshing,
+    // Response is recorded to or replayed from the files in here. See fixtures.rs
+    pub fixtures: &'a config::Fixtures,
+    // Token of the account in this server if logged in. Only sent with the *_authorized requests
+    pub token: Option<&'a str>,
+    // Set when request is actually sent to the server. Response served from the cache should not
+    // be counted in health of the server. Initilize with false
+    pub used_network: AtomicBool,
+    // Items of the response that were repaired or skipped while decoding. See lenient.rs.
+    // Initilize empty
+    pub problems: Mutex<Vec<String>>,
+}
+
+// Tells the source from where to continue fetching the result of same request.
+// Different api paginates differently, so this is only meaningful to the kind of source that
+// returned it
+#[derive(Clone, Debug, PartialEq)]
+pub enum Continuation {
+    // Servers that paginate with page number. eg: invidious search
+    Page(usize),
+    // Servers that return an opaque token to fetch the next batch. eg: `nextpage` in piped
+    Token(String),
+}
+
+// Which list of trending music to fetch. Popular is not a category of trending but the feed of
+// music popular on the server itself (invidious `/popular`). Source that do not have some of
+// these can return the trending of all type instead
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TrendingCategory {
+    Music,
+    // Trending of all type
+    Default,
+    Gaming,
+    Movies,
+    Popular,
+}
+
+// Result of single request that can be continued
+pub struct Batch<T> {
+    pub items: Vec<T>,
+    // None if server says that there is nothing more to fetch
+    pub next: Option<Continuation>,
+}
+
+// Get the source that knows how to talk with server of given api flavour
+// Adding new backend is just implementing MusicSource and returning it from here
+pub fn source_for(api: ApiFlavour) -> &'static dyn MusicSource {
+    match api {
+        ApiFlavour::Invidious => &Invidious,
+        ApiFlavour::Piped => &Piped,
+    }
+}
+
+impl Endpoint<'_> {
+    // Send a GET request to `path` of this server and deserialize the response as Res.
+    // All the network request from sources should be done through this function
+    pub async fn get<Res>(&self, path: &str) -> Result<Res, ReturnAction>
+    where
+        Res: serde::de::DeserializeOwned,
+    {
+        self.get_with_query(path, &[]).await
+    }
+
+    // Same as get() but also append the given query pairs to the url. Values are url encoded
+    // so this should be used whenever the value is not known to be url safe. eg: continuation
+    // token or search query
+    pub async fn get_with_query<Res>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Res, ReturnAction>
+    where
+        Res: serde::de::DeserializeOwned,
+    {
+        let body = self.send(self.server, path, query).await?;
+        self.decode(&body)
+    }
+
+    // Same as get_with_query() but return the response body as text instead of deserializing
+    // it. For response that is not json. eg: WebVTT captions
+    pub async fn get_text(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<String, ReturnAction> {
+        let body = self.send(self.server, path, query).await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    // Same as get_text() but from full `url` that may not be in this server. Some servers
+    // return link to another host for some resources. eg: piped serves captions from its proxy
+    pub async fn get_text_from(&self, url: &str) -> Result<String, ReturnAction> {
+        let body = self.send(url, "", &[]).await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    // Same as get_with_query() but on behalf of the account. Response of the account is never
+    // cached as it changes with every change made to the account
+    pub async fn get_authorized<Res>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Res, ReturnAction>
+    where
+        Res: serde::de::DeserializeOwned,
+    {
+        let body = self.send_authorized(Method::GET, path, query, None).await?;
+        self.decode(&body)
+    }
+
+    // Send `body` as json to `path` on behalf of the account and deserialize the response as Res
+    pub async fn post_authorized<Res>(
+        &self,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> Result<Res, ReturnAction>
+    where
+        Res: serde::de::DeserializeOwned,
+    {
+        let body = self
+            .send_authorized(Method::POST, path, &[], Some(body.to_string()))
+            .await?;
+        self.decode(&body)
+    }
+
+    // Send DELETE request to `path` on behalf of the account. Response is usually empty
+    pub async fn delete_authorized(&self, path: &str) -> Result<(), ReturnAction> {
+        self.send_authorized(Method::DELETE, path, &[], None)
+            .await
+            .map(|_| ())
+    }
+
+    // Same as get_with_query() but the response is first looked up in the cache. Fresh cached
+    // response is returned as is. Stale one is also returned right away but same request is
+    // then sent in background to update the cache for next time.
+    // Response from the server is cached only if it could be deserialized as Res
+    pub async fn get_cached<Res>(
+        &self,
+        kind: cache::Kind,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Res, ReturnAction>
+    where
+        Res: serde::de::DeserializeOwned + 'static,
+    {
+        // Cache would hide the request from being recorded and the response from being replayed
+        if self.fixtures.mode != FixtureMode::Off {
+            return self.get_with_query(path, query).await;
+        }
+
+        let key = cache::key(self.api, path, query);
+
+        if let Some(entry) = cache::get(self.storage, self.cache, &key) {
+            // Cached body may have been saved by older version with different shape of Res.
+            // In that case just treat it as if there was nothing in cache
+            if let Ok(res) = self.decode::<Res>(&entry.body) {
+                if !entry.is_fresh(kind.ttl(self.cache), health::now()) {
+                    self.revalidate::<Res>(key, path, query);
+                }
+                return Ok(res);
+            }
+        }
+
+        let body = self.send(self.server, path, query).await?;
+        let res = self.decode(&body)?;
+        cache::put(self.storage, self.cache, &key, &body);
+        Ok(res)
+    }
+
+    // Same as decode() but also keep what was repaired or skipped in the response. Nothing is
+    // kept of body that could not be decoded at all
+    fn decode<Res>(&self, body: &[u8]) -> Result<Res, ReturnAction>
+    where
+        Res: serde::de::DeserializeOwned,
+    {
+        let (res, problems) = lenient::collect(|| decode(self.server, body));
+        if res.is_ok() {
+            self.problems.lock().unwrap().extend(problems);
+        }
+        res
+    }
+
+    // Send the request to `path` of `server` unless it is being replayed. Response is recorded
+    // if fixtures say so. Resource that is in another host is recorded by its whole url
+    async fn send(
+        &self,
+        server: &str,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Vec<u8>, ReturnAction> {
+        let name = if server == self.server {
+            fixtures::file_name(self.api, path, query)
+        } else {
+            fixtures::file_name(self.api, &(server.to_string() + path), query)
+        };
+        let request = self.client.get(server.to_string() + path).query(query);
+        self.send_recorded(request, server, &name).await
+    }
+
+    // Send the request on behalf of the account to `path` of this server. Fails right away if
+    // this server have no token. Request other than GET changes the account so its method and
+    // body are also part of the file name it is recorded in
+    async fn send_authorized(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<String>,
+    ) -> Result<Vec<u8>, ReturnAction> {
+        let token = self
+            .token
+            .ok_or(ReturnAction::Failed(FetchError::NoAccount))?;
+
+        let name = if method == Method::GET {
+            fixtures::file_name(self.api, path, query)
+        } else {
+            let mut query = query.to_vec();
+            if let Some(ref body) = body {
+                query.push(("body", body));
+            }
+            fixtures::file_name(self.api, &format!("{} {}", method, path), &query)
+        };
+
+        let mut request = self
+            .client
+            .request(method, self.server.to_string() + path)
+            .query(query)
+            .bearer_auth(token);
+        if let Some(body) = body {
+            request = request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body);
+        }
+        self.send_recorded(request, self.server, &name).await
+    }
+
+    // Send the request unless it is being replayed from file `name`. Response is recorded to
+    // that file if fixtures say so
+    async fn send_recorded(
+        &self,
+        request: reqwest::RequestBuilder,
+        server: &str,
+        name: &str,
+    ) -> Result<Vec<u8>, ReturnAction> {
+        if self.fixtures.mode == FixtureMode::Replay {
+            return fixtures::read(&self.fixtures.dir, name);
+        }
+
+        self.used_network.store(true, Ordering::Relaxed);
+        let body = fetch(request, server).await?;
+        if self.fixtures.mode == FixtureMode::Record {
+            fixtures::write(&self.fixtures.dir, name, &body);
+        }
+        Ok(body)
+    }
+
+    // Refetch the stale cached response in background unless it already is. Nothing is waiting
+    // for this request so error is ignored and the stale response is kept as is
+    fn revalidate<Res>(&self, key: String, path: &str, query: &[(&str, &str)])
+    where
+        Res: serde::de::DeserializeOwned + 'static,
+    {
+        let guard = match cache::start_refresh(self.refreshing, &key) {
+            Some(guard) => guard,
+            None => return,
+        };
+        let client = self.client.clone();
+        let storage = Storage::clone(self.storage);
+        let settings = self.cache.clone();
+        let server = self.server.to_string();
+        let path = path.to_string();
+        let query: Vec<(String, String)> = query
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        tokio::spawn(async move {
+            let request = client.get(server.clone() + &path).query(&query);
+            if let Ok(body) = fetch(request, &server).await {
+                if decode::<Res>(&server, &body).is_ok() {
+                    cache::put(&storage, &settings, &key, &body);
+                }
+            }
+            drop(guard);
+        });
+    }
+}
+
+// Send the request to `server` and return the raw response body
+async fn fetch(request: reqwest::RequestBuilder, server: &str) -> Result<Vec<u8>, ReturnAction> {
+    let failed = |error: FetchError| Err(ReturnAction::Failed(error));
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(error) if error.is_timeout() => {
+            return failed(FetchError::Timeout {
+                server: server.to_string(),
+            })
+        }
+        Err(error) => {
+            return failed(FetchError::Network {
+                server: server.to_string(),
+                error,
+            })
+        }
+    };
+
+    let status = response.status();
+    if !status.is_success() {
+        return failed(FetchError::Status {
+            server: server.to_string(),
+            status,
+        });
+    }
+
+    match response.bytes().await {
+        Ok(body) => Ok(body.to_vec()),
+        Err(error) if error.is_timeout() => failed(FetchError::Timeout {
+            server: server.to_string(),
+        }),
+        Err(error) => failed(FetchError::Network {
+            server: server.to_string(),
+            error,
+        }),
+    }
+}
+
+// Deserialize the response body from `server`.
+// This goes through serde_path_to_error so that the error tells where exactly the response
+// differ from what was expected
+fn decode<Res>(server: &str, body: &[u8]) -> Result<Res, ReturnAction>
+where
+    Res: serde::de::DeserializeOwned,
+{
+    let deserializer = &mut serde_json::Deserializer::from_slice(body);
+    serde_path_to_error::deserialize(deserializer).map_err(|error| {
+        ReturnAction::Failed(FetchError::Decode {
+            server: server.to_string(),
+            path: error.path().to_string(),
+            message: error.inner().to_string(),
+        })
+    })
+}
+
+/*
+A MusicSource is one kind of backend api from which music/playlist/artist can be fetched.
+Every method makes (usually) a single request to the server given in Endpoint and converts the
+response to the units defined in this crate.
+Source do not have to paginate the result in chunk of item_per_page or keep the fetched
+result around. Fetcher does all of that and only calls the source when it actually needs
+more data. This means new kind of backend can be added just by implementing this trait and no
+change is needed in the front-end.
+Search methods, playlist content and videos of channel return the result in Batch. `from` is
+None for the first batch and for later batches is the continuation returned by previous batch of
+same request.
+Source that do not support some of the search options can ignore them.
+*/
+pub trait MusicSource: Send + Sync {
+    fn search_music<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        query: &'a str,
+        options: &'a SearchOptions,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>>;
+
+    fn search_playlist<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        query: &'a str,
+        options: &'a SearchOptions,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<PlaylistUnit>>;
+
+    fn search_artist<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        query: &'a str,
+        options: &'a SearchOptions,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<ArtistUnit>>;
+
+    fn get_trending_music<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        category: TrendingCategory,
+    ) -> SourceFuture<'a, Vec<MusicUnit>>;
+
+    fn get_playlist_content<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        playlist_id: &'a str,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>>;
+
+    fn get_playlist_of_channel<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        channel_id: &'a str,
+    ) -> SourceFuture<'a, Vec<PlaylistUnit>>;
+
+    fn get_videos_of_channel<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        channel_id: &'a str,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>>;
+
+    // Latest uploads of the channel along with when those were published. Only the first batch
+    // that server returns is needed
+    fn get_releases_of_channel<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        channel_id: &'a str,
+    ) -> SourceFuture<'a, Vec<Release>>;
+
+    // Completion of partially typed search query
+    fn get_search_suggestions<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        query: &'a str,
+    ) -> SourceFuture<'a, Vec<String>>;
+
+    // Everything about the music to show in details window
+    fn get_video_details<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+    ) -> SourceFuture<'a, VideoDetails>;
+
+    // Music recommended alongside `music_id`
+    fn get_related_music<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+    ) -> SourceFuture<'a, Vec<MusicUnit>>;
+
+    // Music similar to `music_id`. Radio never ends so every batch should continue with some
+    // music of it (usually the last one) as the seed of next batch. Same music may be returned
+    // again in later batch, fetcher takes care of dropping those
+    fn get_radio<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+        from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>>;
+
+    // Caption tracks available for the music. Content of track is fetched with get_captions()
+    fn get_caption_tracks<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+    ) -> SourceFuture<'a, Vec<CaptionTrack>>;
+
+    // Content of caption `track` of the music in WebVTT format. `track` is one of those returned
+    // by get_caption_tracks() of same source
+    fn get_captions<'a>(
+        &'a self,
+        endpoint: &'a Endpoint<'a>,
+        music_id: &'a str,
+        track: &'a CaptionTrack,
+    ) -> SourceFuture<'a, String>;
+
+    // Rest of the methods are on behalf of the account whose token is in the endpoint. Source
+    // that do not have accounts can leave these as is and they fail as if not logged in
+
+    // Videos uploaded by the channels account is subscribed to. Newest first
+    fn get_feed<'a>(
+        &'a self,
+        _endpoint: &'a Endpoint<'a>,
+        _from: Option<&'a Continuation>,
+    ) -> SourceFuture<'a, Batch<MusicUnit>> {
+        no_account()
+    }
+
+    fn get_subscriptions<'a>(
+        &'a self,
+        _endpoint: &'a Endpoint<'a>,
+    ) -> SourceFuture<'a, Vec<ArtistUnit>> {
+        no_account()
+    }
+
+    // Playlists created by the account. These only exist in the server of the endpoint
+    fn get_account_playlists<'a>(
+        &'a self,
+        _endpoint: &'a Endpoint<'a>,
+    ) -> SourceFuture<'a, Vec<PlaylistUnit>> {
+        no_account()
+    }
+
+    // Content of playlist returned by get_account_playlists(). Unlike get_playlist_content()
+    // this also works for private playlist
+    fn get_account_playlist_content<'a>(
+        &'a self,
+        _endpoint: &'a Endpoint<'a>,
+        _playlist_id: &'a str,
+    ) -> SourceFuture<'a, Vec<MusicUnit>> {
+        no_account()
+    }
+
+    // Create an empty playlist of given name and return it
+    fn create_playlist<'a>(
+        &'a self,
+        _endpoint: &'a Endpoint<'a>,
+        _name: &'a str,
+    ) -> SourceFuture<'a, PlaylistUnit> {
+        no_account()
+    }
+
+    fn add_to_playlist<'a>(
+        &'a self,
+        _endpoint: &'a Endpoint<'a>,
+        _playlist_id: &'a str,
+        _music_id: &'a str,
+    ) -> SourceFuture<'a, ()> {
+        no_account()
+    }
+
+    fn remove_from_playlist<'a>(
+        &'a self,
+        _endpoint: &'a Endpoint<'a>,
+        _playlist_id: &'a str,
+        _music_id: &'a str,
+    ) -> SourceFuture<'a, ()> {
+        no_account()
+    }
+}
+
+// Default of account methods in MusicSource
+fn no_account<'a, T: 'a>() -> SourceFuture<'a, T> {
+    Box::pin(async { Err(ReturnAction::Failed(FetchError::NoAccount)) })
+}
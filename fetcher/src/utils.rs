@@ -1,18 +1,23 @@
-use crate::{Fetcher, ReturnAction};
+use crate::account;
+use crate::cache;
+use crate::captions::{self, CaptionLine, CaptionTrack};
+use crate::releases;
+use crate::search::SearchOptions;
+use crate::source::{self, ApiFlavour, Endpoint};
+use crate::{
+    health, ExtendClientBuilder, ExtendDuration, FetchError, Fetcher, FetcherBuilder, ReturnAction,
+};
 use config::initilize::{
-    CONFIG, STORAGE, TB_FAVOURATES_ARTIST, TB_FAVOURATES_MUSIC, TB_FAVOURATES_PLAYLIST,
+    Storage, CONFIG, STORAGE, TB_FAVOURATES_ARTIST, TB_FAVOURATES_MUSIC, TB_FAVOURATES_PLAYLIST,
 };
 use reqwest;
 use std::iter::DoubleEndedIterator;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+// Problems of responses kept by a fetcher until they are taken. See Fetcher::take_problems()
+const MAX_PROBLEMS: usize = 50;
 const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/92.0.4515.131 Safari/537.36";
-const FIELDS: [&str; 3] = [
-    "videoId,title,author,lengthSeconds",
-    "title,playlistId,author,videoCount",
-    "author,authorId,videoCount",
-];
-const FILTER_TYPE: [&str; 3] = ["music", "playlist", "channel"];
 
 impl crate::ExtendDuration for Duration {
     fn to_string(self) -> String {
@@ -61,163 +66,497 @@ impl crate::ExtendDuration for Duration {
     }
 }
 
+impl crate::ExtendClientBuilder for reqwest::ClientBuilder {
+    fn with_network(self, network: &config::Network) -> Self {
+        let proxy = match network.proxy() {
+            Some(proxy) => proxy,
+            None => return self,
+        };
+
+        match reqwest::Proxy::all(proxy) {
+            Ok(proxy) => {
+                let no_proxy = reqwest::NoProxy::from_string(&network.no_proxy.join(","));
+                self.proxy(proxy.no_proxy(no_proxy))
+            }
+            Err(err) => {
+                eprintln!(
+                    "Invalid proxy {proxy} in config. Connecting directly. Error: {err}",
+                    proxy = proxy,
+                    err = err
+                );
+                self
+            }
+        }
+    }
+}
+
 impl Default for Fetcher {
     fn default() -> Self {
+        FetcherBuilder::from_config().build()
+    }
+}
+
+impl FetcherBuilder {
+    // Builder with everything taken from CONFIG and STORAGE. Anything can still be replaced
+    // before building. eg: fixtures selected from command line
+    pub fn from_config() -> Self {
+        FetcherBuilder::default()
+            .servers(CONFIG.servers.list.clone())
+            .region(&CONFIG.constants.region)
+            .item_per_page(CONFIG.constants.item_per_list)
+            .timeout(Duration::from_millis(
+                CONFIG.constants.server_time_out as u64,
+            ))
+            .storage(Storage::clone(&STORAGE))
+            .cache(CONFIG.cache.clone())
+            .fixtures(CONFIG.fixtures.clone())
+            .network(CONFIG.network.clone())
+    }
+
+    // Health shared by share_with() before this is of its servers and is not used with these
+    pub fn servers(mut self, servers: Vec<config::Server>) -> Self {
+        self.servers = Some(servers);
+        self.health = None;
+        self
+    }
+
+    pub fn region(mut self, region: &str) -> Self {
+        self.region = Some(region.to_string());
+        self
+    }
+
+    pub fn item_per_page(mut self, item_per_page: usize) -> Self {
+        self.item_per_page = Some(item_per_page);
+        self
+    }
+
+    // Used both for connecting and for the whole response to arrive after that
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    // Storage must already have the tables. See config::ConfigContainer::create_tables()
+    pub fn storage(mut self, storage: Storage) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn cache(mut self, cache: config::Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn fixtures(mut self, fixtures: config::Fixtures) -> Self {
+        self.fixtures = Some(fixtures);
+        self
+    }
+
+    // Proxy to connect to the servers through
+    pub fn network(mut self, network: config::Network) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    // Send request to the servers of `fetcher` and share the server health with it so that a
+    // server found dead by one of them is avoided by both. Stale cached response being refreshed
+    // by one is not refreshed again by the other. This replaces the servers given before
+    pub fn share_with(mut self, fetcher: &Fetcher) -> Self {
+        self.servers = Some(fetcher.servers.clone());
+        self.health = Some(health::Shared::clone(&fetcher.health));
+        self.refreshing = Some(cache::Refreshing::clone(&fetcher.refreshing));
+        self
+    }
+
+    pub fn build(self) -> Fetcher {
+        let servers = self
+            .servers
+            .unwrap_or_else(|| config::Servers::default().list);
+        let constants = config::Constants::default();
+        let timeout = self
+            .timeout
+            .unwrap_or_else(|| Duration::from_millis(constants.server_time_out as u64));
+        let storage = self.storage.unwrap_or_else(in_memory_storage);
+
         super::Fetcher {
-            trending_now: None,
+            trending_now: Default::default(),
             playlist_content: super::PlaylistRes::default(),
             artist_content: super::ArtistRes::default(),
+            radio: Default::default(),
             search_res: super::SearchRes::default(),
-            servers: &CONFIG.servers.list,
+            feed: Default::default(),
+            releases: Default::default(),
+            health: match self.health {
+                Some(health) => health,
+                None => Arc::new(Mutex::new(health::load(
+                    &storage,
+                    servers.iter().map(|server| &server.url),
+                ))),
+            },
+            tokens: account::load(&storage, servers.iter().map(|server| &server.url)),
+            servers,
             client: reqwest::ClientBuilder::default()
                 .user_agent(USER_AGENT)
                 .gzip(true)
+                // Without these a hung server keeps the request pending forever
+                .connect_timeout(timeout)
+                .timeout(timeout)
+                .with_network(&self.network.unwrap_or_default())
                 .build()
                 .unwrap(),
             active_server_index: 0,
-            region: &CONFIG.constants.region,
-            item_per_page: CONFIG.constants.item_per_list,
+            region: self.region.unwrap_or(constants.region),
+            item_per_page: self.item_per_page.unwrap_or(constants.item_per_list),
+            storage,
+            cache: self.cache.unwrap_or_default(),
+            refreshing: self.refreshing.unwrap_or_default(),
+            // Default of config::Fixtures would create the config directory
+            fixtures: self.fixtures.unwrap_or(config::Fixtures {
+                mode: config::FixtureMode::Off,
+                dir: String::new(),
+            }),
+            problems: Vec::new(),
         }
     }
 }
 
+// Fresh storage that lives only as long as the fetcher. Only fails when sqlite itself is broken
+fn in_memory_storage() -> Storage {
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    config::ConfigContainer::create_tables(&conn).unwrap();
+    Arc::new(Mutex::new(conn))
+}
+
+// Send the request through the source of the healthiest server.
+// $call is called with reference to the source and an Endpoint describing the server and should
+// return the future from one of the method of MusicSource.
+// When $api is Some only the server of that api is selected. This is needed when continuing
+// the previous request as continuation is only valid for the api that returned it.
+// When request to a server fails because of the server (see FetchError::is_server_fault), it is
+// recorded in server health and same request is sent to next healthiest server. Other errors
+// are returned right away. At most 1 + $retry_for servers are tried before giving up with
+// ReturnAction::Failed carrying the error of last tried server
+// When `account` is given instead of $api only the server that have token of the account is
+// selected. See account.rs
+// This is a macro instead of function because the future returned by $call borrows from the
+// closure arguments and that can't be expressed easily in closure signature
+macro_rules! dispatch {
+    ($fetcher: expr, $retry_for: expr, |$source: ident, $endpoint: ident| $call: expr) => {
+        dispatch!(
+            "@internal",
+            $fetcher,
+            $retry_for,
+            None,
+            false,
+            |$source, $endpoint| $call
+        )
+    };
+    ($fetcher: expr, $retry_for: expr, account, |$source: ident, $endpoint: ident| $call: expr) => {
+        dispatch!(
+            "@internal",
+            $fetcher,
+            $retry_for,
+            None,
+            true,
+            |$source, $endpoint| $call
+        )
+    };
+    ($fetcher: expr, $retry_for: expr, $api: expr, |$source: ident, $endpoint: ident| $call: expr) => {
+        dispatch!(
+            "@internal",
+            $fetcher,
+            $retry_for,
+            $api,
+            false,
+            |$source, $endpoint| $call
+        )
+    };
+
+    ("@internal", $fetcher: expr, $retry_for: expr, $api: expr, $account: expr, |$source: ident, $endpoint: ident| $call: expr) => {{
+        let api: Option<ApiFlavour> = $api;
+        let account: bool = $account;
+        let mut tried: Vec<usize> = Vec::new();
+        let mut last_error = if account {
+            FetchError::NoAccount
+        } else {
+            FetchError::NoServer
+        };
+
+        loop {
+            let index = match $fetcher.pick_server(api, account, &tried) {
+                Some(index) => index,
+                None => break Err(ReturnAction::Failed(last_error)),
+            };
+            tried.push(index);
+            $fetcher.active_server_index = index;
+
+            let server = &$fetcher.servers[index];
+            let $endpoint = Endpoint {
+                client: &$fetcher.client,
+                server: &server.url,
+                region: &$fetcher.region,
+                api: server.api,
+                storage: &$fetcher.storage,
+                cache: &$fetcher.cache,
+                refreshing: &$fetcher.refreshing,
+                fixtures: &$fetcher.fixtures,
+                token: $fetcher.tokens[index].as_deref(),
+                used_network: Default::default(),
+                problems: Default::default(),
+            };
+            let $source = source::source_for(server.api);
+
+            let started = std::time::Instant::now();
+            let res = $call.await;
+            // Fields are used directly as $endpoint still borrows the rest of $fetcher
+            $fetcher
+                .problems
+                .append(&mut $endpoint.problems.lock().unwrap());
+            let excess = $fetcher.problems.len().saturating_sub(MAX_PROBLEMS);
+            $fetcher.problems.drain(..excess);
+
+            match res {
+                Err(ReturnAction::Failed(error)) if !error.is_server_fault() => {
+                    break Err(ReturnAction::Failed(error))
+                }
+                Err(ReturnAction::Failed(error)) => {
+                    $fetcher.record_failure(index);
+                    if tried.len() > $retry_for {
+                        break Err(ReturnAction::Failed(error));
+                    }
+                    last_error = error;
+                }
+                Err(ReturnAction::Retry) => {
+                    $fetcher.record_failure(index);
+                    if tried.len() > $retry_for {
+                        break Err(ReturnAction::Retry);
+                    }
+                }
+                // Response served from the cache says nothing about the server
+                res if !$endpoint
+                    .used_network
+                    .load(std::sync::atomic::Ordering::Relaxed) =>
+                {
+                    break res
+                }
+                res => {
+                    $fetcher.record_success(index, started.elapsed());
+                    break res;
+                }
+            }
+        }
+    }};
+}
+
+// Fetch as many batch into $store_target (a paging::Paged) as needed to fill local $page and
+// return that page. Server is asked for the batch continued from where it said last time,
+// which is passed to $call as $from. See paging.rs
+// Returns ReturnAction::EOR when there is nothing in that page
+// `account` is passed to dispatch! as is. With `unique` item already in $store_target is
+// dropped from the batch. See paging::Paged::extend_unique
+macro_rules! fill_page {
+    ($fetcher: expr, $page: expr, $store_target: expr, |$source: ident, $endpoint: ident, $from: ident| $call: expr) => {
+        fill_page!(
+            "@internal",
+            $fetcher,
+            $page,
+            $store_target,
+            false,
+            extend,
+            |$source, $endpoint, $from| $call
+        )
+    };
+    ($fetcher: expr, $page: expr, $store_target: expr, account, |$source: ident, $endpoint: ident, $from: ident| $call: expr) => {
+        fill_page!(
+            "@internal",
+            $fetcher,
+            $page,
+            $store_target,
+            true,
+            extend,
+            |$source, $endpoint, $from| $call
+        )
+    };
+    ($fetcher: expr, $page: expr, $store_target: expr, unique, |$source: ident, $endpoint: ident, $from: ident| $call: expr) => {
+        fill_page!(
+            "@internal",
+            $fetcher,
+            $page,
+            $store_target,
+            false,
+            extend_unique,
+            |$source, $endpoint, $from| $call
+        )
+    };
+
+    ("@internal", $fetcher: expr, $page: expr, $store_target: expr, $account: expr, $extend: ident, |$source: ident, $endpoint: ident, $from: ident| $call: expr) => {{
+        while $store_target.needs_more($page, $fetcher.item_per_page) {
+            // Cloned so that nothing is lost if this future is dropped before the response arrives
+            let next = $store_target.next().cloned();
+            let $from = next.as_ref().map(|(_, continuation)| continuation);
+            let obj = dispatch!(
+                "@internal",
+                $fetcher,
+                1,
+                next.as_ref().map(|(api, _)| *api),
+                $account,
+                |$source, $endpoint| $call
+            );
+            match obj {
+                Ok(batch) => {
+                    let api = $fetcher.active_api();
+                    $store_target.$extend(batch, api);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        match $store_target.page($page, $fetcher.item_per_page) {
+            Some(items) => Ok(items.to_vec()),
+            None => Err(ReturnAction::EOR),
+        }
+    }};
+}
+
 macro_rules! search {
-    ("music", $fetcher: expr, $query: expr, $page: expr) => {
+    ("music", $fetcher: expr, $query: expr, $options: expr, $page: expr) => {
         search!(
             "@internal-core",
             $fetcher,
             $query,
+            $options,
             $page,
             $fetcher.search_res.music,
-            0,
-            super::MusicUnit
+            search_music
         )
     };
-    ("playlist", $fetcher: expr, $query: expr, $page: expr) => {
+    ("playlist", $fetcher: expr, $query: expr, $options: expr, $page: expr) => {
         search!(
             "@internal-core",
             $fetcher,
             $query,
+            $options,
             $page,
             $fetcher.search_res.playlist,
-            1,
-            super::PlaylistUnit
+            search_playlist
         )
     };
-    ("artist", $fetcher: expr, $query: expr, $page: expr) => {
+    ("artist", $fetcher: expr, $query: expr, $options: expr, $page: expr) => {
         search!(
             "@internal-core",
             $fetcher,
             $query,
+            $options,
             $page,
             $fetcher.search_res.artist,
-            2,
-            super::ArtistUnit
+            search_artist
         )
     };
 
-    ("@internal-core", $fetcher: expr, $query: expr, $page: expr, $store_target: expr, $filter_index: expr, $unit_type: ty) => {{
-        let suffix = format!(
-            "/search?q={query}&type={s_type}&{region}&page={page}&fields={fields}",
-            query = $query,
-            s_type = FILTER_TYPE[$filter_index],
-            region = $fetcher.region,
-            fields = FIELDS[$filter_index],
-            page = $page
-        );
-        let lower_limit = $page * $fetcher.item_per_page;
-        let mut upper_limit =
-            std::cmp::min($store_target.len(), lower_limit + $fetcher.item_per_page);
-
-        let is_new_query = *$query != $fetcher.search_res.query;
-        let is_new_type = $fetcher.search_res.last_fetched != $filter_index;
-        let insufficient_data =
-            upper_limit.checked_sub(lower_limit).unwrap_or(0) < $fetcher.item_per_page;
-
-        $fetcher.search_res.last_fetched = $filter_index;
-        if is_new_query || insufficient_data || is_new_type {
-            let obj = $fetcher.send_request::<Vec<$unit_type>>(&suffix, 1).await;
-            if is_new_query || is_new_type {
-                $store_target.clear();
-            }
-            match obj {
-                Ok(data) => {
-                    $fetcher.search_res.query = $query.to_string();
-                    $store_target.extend_from_slice(data.as_slice());
-                    upper_limit =
-                        std::cmp::min($store_target.len(), lower_limit + $fetcher.item_per_page);
-                }
-                Err(e) => return Err(e),
-            }
+    ("@internal-core", $fetcher: expr, $query: expr, $options: expr, $page: expr, $store_target: expr, $method: ident) => {{
+        // Result of every type is kept until query or options change so that switching between
+        // music/playlist/artist result do not refetch everything
+        if *$query != $fetcher.search_res.query || *$options != $fetcher.search_res.options {
+            $fetcher.search_res = super::SearchRes {
+                query: $query.to_string(),
+                options: $options.clone(),
+                ..Default::default()
+            };
         }
 
-        if upper_limit > lower_limit {
-            Ok($store_target[lower_limit..upper_limit].to_vec())
-        } else {
-            Err(ReturnAction::EOR)
-        }
+        fill_page!($fetcher, $page, $store_target, |source, endpoint, from| {
+            source.$method(&endpoint, $query, $options, from)
+        })
     }};
 }
 
 impl Fetcher {
-    pub fn change_server(&mut self) {
-        self.active_server_index = (self.active_server_index + 1) % self.servers.len();
+    // Index of the healthiest server that is not in `tried`. If api is Some only server of that
+    // api is considered and if account is true only the account_server(). See health::pick
+    fn pick_server(
+        &self,
+        api: Option<ApiFlavour>,
+        account: bool,
+        tried: &[usize],
+    ) -> Option<usize> {
+        let account_server = self.account_server();
+        health::pick(
+            &self.health.lock().unwrap(),
+            self.active_server_index,
+            health::now(),
+            |index| {
+                !tried.contains(&index)
+                    && api.map_or(true, |api| self.servers[index].api == api)
+                    && (!account || account_server == Some(index))
+            },
+        )
     }
 
-    // All the request should be send from this function
-    async fn send_request<'de, Res>(
-        &mut self,
-        path: &str,
-        retry_for: i32,
-    ) -> Result<Res, ReturnAction>
-    where
-        Res: serde::de::DeserializeOwned,
-    {
-        self.change_server();
+    // Index of the server whose account is used. Playlists of an account only exist in its own
+    // server, so even when logged in to several servers only the first of them in servers[] is
+    // used so that every request of the account reaches the same server
+    fn account_server(&self) -> Option<usize> {
+        self.tokens.iter().position(Option::is_some)
+    }
 
-        let url = self.servers[self.active_server_index].to_string() + path;
-        let res = self.client.get(url).send().await;
+    fn record_success(&mut self, index: usize, latency: Duration) {
+        let mut records = self.health.lock().unwrap();
+        records[index].record_success(latency);
+        health::save(&self.storage, &self.servers[index].url, &records[index]);
+    }
 
-        match res {
-            Ok(response) => {
-                if let Ok(obj) = response.json::<Res>().await {
-                    Ok(obj)
-                } else {
-                    Err(ReturnAction::Failed)
-                }
-            }
-            Err(_) if retry_for > 0 => {
-                self.change_server();
-                Err(ReturnAction::Retry)
-            }
-            Err(_) => Err(ReturnAction::Failed),
-        }
+    fn record_failure(&mut self, index: usize) {
+        let mut records = self.health.lock().unwrap();
+        records[index].record_failure(health::now());
+        health::save(&self.storage, &self.servers[index].url, &records[index]);
+    }
+
+    // Items that were repaired or skipped in the responses since this was last called. Every
+    // item a server returns is not always valid and instead of failing the whole response
+    // such item is fixed or left out. See lenient.rs
+    pub fn take_problems(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.problems)
+    }
+
+    // Api of the server to which last request was made
+    fn active_api(&self) -> ApiFlavour {
+        self.servers[self.active_server_index].api
+    }
+
+    // Region trending and search result are fetched for
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+
+    // Change the region without restarting. Trending and search result fetched for previous
+    // region are dropped so that they are fetched again for this region. Response cached in
+    // storage are per region so those of previous region are just left there
+    pub fn set_region(&mut self, region: &str) {
+        self.region = region.to_string();
+        self.trending_now.clear();
+        self.search_res = super::SearchRes::default();
     }
 
     pub async fn get_trending_music(
         &mut self,
+        category: source::TrendingCategory,
         page: usize,
     ) -> Result<Vec<super::MusicUnit>, ReturnAction> {
         let lower_limit = self.item_per_page * page;
 
-        if self.trending_now.is_none() {
-            let suffix = format!(
-                "/trending?type=Music&region={region}&fields={music_field}",
-                region = self.region,
-                music_field = FIELDS[0]
-            );
-
-            let obj = self.send_request::<Vec<super::MusicUnit>>(&suffix, 2).await;
+        if !self.trending_now.contains_key(&category) {
+            let obj = dispatch!(self, 2, |source, endpoint| source
+                .get_trending_music(&endpoint, category));
             match obj {
                 Ok(mut res) => {
                     res.shrink_to_fit();
-                    self.trending_now = Some(res);
+                    self.trending_now.insert(category, res);
                 }
                 Err(e) => return Err(e),
             }
         }
 
-        let trending_now = self.trending_now.as_ref().unwrap();
+        let trending_now = &self.trending_now[&category];
         let upper_limit = std::cmp::min(trending_now.len(), lower_limit + self.item_per_page);
 
         if lower_limit >= upper_limit {
@@ -232,40 +571,21 @@ impl Fetcher {
         playlist_id: &str,
         page: usize,
     ) -> Result<Vec<super::MusicUnit>, ReturnAction> {
-        let lower_limit = page * self.item_per_page;
-
-        let is_new_id = *playlist_id != self.playlist_content.id;
-        if is_new_id {
-            self.playlist_content.id = playlist_id.to_string();
-            let suffix = format!(
-                "/playlists/{playlist_id}?fields=videos({music_field})",
-                playlist_id = playlist_id,
-                music_field = FIELDS[0]
-            );
-
-            let obj = self
-                .send_request::<super::FetchPlaylistContentRes>(&suffix, 1)
-                .await;
-            match obj {
-                Ok(mut data) => {
-                    data.videos.shrink_to_fit();
-                    self.playlist_content.music = data.videos;
-                }
-                Err(e) => return Err(e),
-            }
+        // Batches fetched so far are only dropped when another playlist is asked for. If the
+        // request is then cancelled or failed, store is empty and is simply refetched next time
+        if *playlist_id != self.playlist_content.id {
+            self.playlist_content = super::PlaylistRes {
+                id: playlist_id.to_string(),
+                ..Default::default()
+            };
         }
 
-        let upper_limit = std::cmp::min(
-            self.playlist_content.music.len(),
-            lower_limit + self.item_per_page,
-        );
-        if lower_limit >= upper_limit {
-            Err(ReturnAction::EOR)
-        } else {
-            let mut res = self.playlist_content.music[lower_limit..upper_limit].to_vec();
-            res.shrink_to_fit();
-            Ok(res)
-        }
+        fill_page!(
+            self,
+            page,
+            self.playlist_content.music,
+            |source, endpoint, from| source.get_playlist_content(&endpoint, playlist_id, from)
+        )
     }
 
     pub async fn get_playlist_of_channel(
@@ -277,20 +597,13 @@ impl Fetcher {
 
         let is_new_id = *channel_id != self.artist_content.playlist.0;
         if is_new_id || self.artist_content.playlist.1.is_empty() {
-            self.artist_content.playlist.0 = channel_id.to_string();
-            let suffix = format!(
-                "/channels/{channel_id}/playlists?fields=playlists({channel_fields})",
-                channel_id = channel_id,
-                channel_fields = FIELDS[1],
-            );
-
-            let obj = self
-                .send_request::<super::FetchArtistPlaylist>(&suffix, 1)
-                .await;
+            let obj = dispatch!(self, 1, |source, endpoint| source
+                .get_playlist_of_channel(&endpoint, channel_id));
             match obj {
                 Ok(mut data) => {
-                    data.playlists.shrink_to_fit();
-                    self.artist_content.playlist.1 = data.playlists;
+                    data.shrink_to_fit();
+                    self.artist_content.playlist.0 = channel_id.to_string();
+                    self.artist_content.playlist.1 = data;
                 }
                 Err(e) => return Err(e),
             }
@@ -314,38 +627,37 @@ impl Fetcher {
         channel_id: &str,
         page: usize,
     ) -> Result<Vec<super::MusicUnit>, ReturnAction> {
-        let lower_limit = page * self.item_per_page;
+        // Same as in get_playlist_content
+        if *channel_id != self.artist_content.music.0 {
+            self.artist_content.music = (channel_id.to_string(), Default::default());
+        }
 
-        let is_new_id = *channel_id != self.artist_content.music.0;
-        if is_new_id || self.artist_content.music.1.is_empty() {
-            self.artist_content.music.0 = channel_id.to_string();
-            let suffix = format!(
-                "/channels/{channel_id}/videos&fields={music_field}",
-                channel_id = channel_id,
-                music_field = FIELDS[0]
-            );
+        fill_page!(
+            self,
+            page,
+            self.artist_content.music.1,
+            |source, endpoint, from| source.get_videos_of_channel(&endpoint, channel_id, from)
+        )
+    }
 
-            let obj = self.send_request::<Vec<super::MusicUnit>>(&suffix, 1).await;
-            match obj {
-                Ok(mut data) => {
-                    data.shrink_to_fit();
-                    self.artist_content.music.1 = data;
-                }
-                Err(e) => return Err(e),
-            }
+    pub async fn get_radio(
+        &mut self,
+        music_id: &str,
+        page: usize,
+    ) -> Result<Vec<super::MusicUnit>, ReturnAction> {
+        // Same as in get_playlist_content
+        if *music_id != self.radio.0 {
+            self.radio = (music_id.to_string(), Default::default());
         }
 
-        let upper_limit = std::cmp::min(
-            self.artist_content.music.1.len(),
-            lower_limit + self.item_per_page,
-        );
-        if lower_limit >= upper_limit {
-            Err(ReturnAction::EOR)
-        } else {
-            let mut res = self.artist_content.music.1[lower_limit..upper_limit].to_vec();
-            res.shrink_to_fit();
-            Ok(res)
-        }
+        // Consecutive batch of radio overlap a lot. Only keep the music not yet in radio
+        fill_page!(
+            self,
+            page,
+            self.radio.1,
+            unique,
+            |source, endpoint, from| { source.get_radio(&endpoint, music_id, from) }
+        )
     }
 
     pub async fn get_favourates_music(
@@ -353,7 +665,7 @@ impl Fetcher {
         page: usize,
     ) -> Result<Vec<super::MusicUnit>, ReturnAction> {
         let lower_limit = page * self.item_per_page;
-        let conn = STORAGE.lock().unwrap();
+        let conn = self.storage.lock().unwrap();
 
         let query = format!(
             "
@@ -374,16 +686,24 @@ impl Fetcher {
                     "Error preparing select statement for favourates music. Error: {err}",
                     err = err
                 );
-                return Err(ReturnAction::Failed);
+                return Err(ReturnAction::Failed(FetchError::Storage(err)));
             }
         };
 
         let results = stmt.query_map([], |row| {
+            // Duration is saved as text like `3:05`. Live stream is saved as `LIVE` instead
+            let duration: String = row.get(3).unwrap_or("3:0".into());
+            let live = duration == "LIVE";
             Ok(super::MusicUnit {
                 id: row.get(0).unwrap_or_default(),
                 name: row.get(1).unwrap_or("SQL_ERROR".into()),
                 artist: row.get(2).unwrap_or("SQL_ERROR".into()),
-                duration: row.get(3).unwrap_or("3:0".into()),
+                duration: if live {
+                    Duration::ZERO
+                } else {
+                    Duration::from_string(&duration)
+                },
+                live,
             })
         });
 
@@ -393,7 +713,7 @@ impl Fetcher {
                     "Cannot get results of favourates music. Error: {err}",
                     err = err
                 );
-                return Err(ReturnAction::Failed);
+                return Err(ReturnAction::Failed(FetchError::Storage(err)));
             }
             Ok(results) => {
                 let mut return_res: Vec<super::MusicUnit> = Vec::with_capacity(self.item_per_page);
@@ -417,7 +737,7 @@ impl Fetcher {
         page: usize,
     ) -> Result<Vec<super::PlaylistUnit>, ReturnAction> {
         let lower_limit = page * self.item_per_page;
-        let conn = STORAGE.lock().unwrap();
+        let conn = self.storage.lock().unwrap();
 
         let query = format!(
             "
@@ -438,7 +758,7 @@ impl Fetcher {
                     "Error preparing select statement for favourates playlist. Error: {err}",
                     err = err
                 );
-                return Err(ReturnAction::Failed);
+                return Err(ReturnAction::Failed(FetchError::Storage(err)));
             }
         };
 
@@ -457,7 +777,7 @@ impl Fetcher {
                     "Cannot get results of favourates music. Error: {err}",
                     err = err
                 );
-                return Err(ReturnAction::Failed);
+                return Err(ReturnAction::Failed(FetchError::Storage(err)));
             }
             Ok(results) => {
                 let mut return_res: Vec<super::PlaylistUnit> =
@@ -482,7 +802,7 @@ impl Fetcher {
         page: usize,
     ) -> Result<Vec<super::ArtistUnit>, ReturnAction> {
         let lower_limit = page * self.item_per_page;
-        let conn = STORAGE.lock().unwrap();
+        let conn = self.storage.lock().unwrap();
 
         let query = format!(
             "
@@ -503,7 +823,7 @@ impl Fetcher {
                     "Error preparing select statement for favourates artist. Error: {err}",
                     err = err
                 );
-                return Err(ReturnAction::Failed);
+                return Err(ReturnAction::Failed(FetchError::Storage(err)));
             }
         };
 
@@ -521,7 +841,7 @@ impl Fetcher {
                     "Cannot get results of favourates artist. Error: {err}",
                     err = err
                 );
-                return Err(ReturnAction::Failed);
+                return Err(ReturnAction::Failed(FetchError::Storage(err)));
             }
             Ok(results) => {
                 let mut return_res: Vec<super::ArtistUnit> = Vec::with_capacity(self.item_per_page);
@@ -540,27 +860,347 @@ impl Fetcher {
         Ok(res)
     }
 
+    // Latest uploads of favourate artists, newest first. Uploads of every artist are fetched at
+    // once and kept for a while so that paging back and forth do not fetch them again.
+    // Music in the returned page are remembered as seen. See releases.rs
+    pub async fn get_new_releases(
+        &mut self,
+        page: usize,
+    ) -> Result<Vec<super::MusicUnit>, ReturnAction> {
+        let artists = releases::followed(&self.storage)
+            .map_err(|err| ReturnAction::Failed(FetchError::Storage(err)))?;
+        if artists.is_empty() {
+            return Err(ReturnAction::EOR);
+        }
+
+        if !self.releases.is_fresh_for(&artists) {
+            let music = dispatch!(self, 1, |source, endpoint| releases::fetch_all(
+                source, &endpoint, &artists
+            ))?;
+            self.releases = releases::Fetched {
+                artists,
+                at: Some(std::time::Instant::now()),
+                music,
+            };
+        }
+
+        let music = page_of(self.releases.music.clone(), page, self.item_per_page)?;
+        releases::mark_seen(&self.storage, &music, health::now());
+        Ok(music)
+    }
+
+    // Suggestions to complete the partially typed search query. Nothing is kept in fetcher
+    // as every keystroke makes new query anyway
+    pub async fn get_search_suggestions(
+        &mut self,
+        query: &str,
+    ) -> Result<Vec<String>, ReturnAction> {
+        dispatch!(self, 0, |source, endpoint| source
+            .get_search_suggestions(&endpoint, query))
+    }
+
+    pub async fn get_video_details(
+        &mut self,
+        music_id: &str,
+    ) -> Result<crate::details::VideoDetails, ReturnAction> {
+        dispatch!(self, 1, |source, endpoint| source
+            .get_video_details(&endpoint, music_id))
+    }
+
+    pub async fn get_related_music(
+        &mut self,
+        music_id: &str,
+    ) -> Result<Vec<super::MusicUnit>, ReturnAction> {
+        dispatch!(self, 1, |source, endpoint| source
+            .get_related_music(&endpoint, music_id))
+    }
+
+    // Caption tracks of the music. Tracks whose content was saved earlier are returned when
+    // no server could be reached so that captions also work offline
+    pub async fn get_caption_tracks(
+        &mut self,
+        music_id: &str,
+    ) -> Result<Vec<CaptionTrack>, ReturnAction> {
+        let res = dispatch!(self, 1, |source, endpoint| source
+            .get_caption_tracks(&endpoint, music_id));
+        match res {
+            Err(ReturnAction::Failed(error)) => {
+                let saved = captions::saved_tracks(&self.storage, music_id);
+                if saved.is_empty() {
+                    Err(ReturnAction::Failed(error))
+                } else {
+                    Ok(saved)
+                }
+            }
+            res => res,
+        }
+    }
+
+    // Timed lines of caption `track` of the music. Content of track is saved in storage once
+    // fetched and read from there afterwards
+    pub async fn get_captions(
+        &mut self,
+        music_id: &str,
+        track: &CaptionTrack,
+    ) -> Result<Vec<CaptionLine>, ReturnAction> {
+        if let Some(body) = captions::load(&self.storage, music_id, &track.label) {
+            return Ok(captions::parse_webvtt(&body));
+        }
+
+        // Url of the track is only understood by the same api that returned it
+        let body = dispatch!(self, 1, track.api, |source, endpoint| source
+            .get_captions(&endpoint, music_id, track))?;
+        captions::save(&self.storage, music_id, track, &body);
+        Ok(captions::parse_webvtt(&body))
+    }
+
     pub async fn search_music(
         &mut self,
         query: &str,
+        options: &SearchOptions,
         page: usize,
     ) -> Result<Vec<super::MusicUnit>, ReturnAction> {
-        search!("music", self, query, page)
+        search!("music", self, query, options, page)
     }
 
     pub async fn search_playlist(
         &mut self,
         query: &str,
+        options: &SearchOptions,
         page: usize,
     ) -> Result<Vec<super::PlaylistUnit>, ReturnAction> {
-        search!("playlist", self, query, page)
+        search!("playlist", self, query, options, page)
     }
 
     pub async fn search_artist(
         &mut self,
         query: &str,
+        options: &SearchOptions,
         page: usize,
     ) -> Result<Vec<super::ArtistUnit>, ReturnAction> {
-        search!("artist", self, query, page)
+        search!("artist", self, query, options, page)
+    }
+
+    // true if logged in to account of some server. See account.rs
+    pub fn has_account(&self) -> bool {
+        self.account_server().is_some()
+    }
+
+    // Videos of the channels the account is subscribed to. New videos keep coming so the feed is
+    // fetched again whenever first page is asked
+    pub async fn get_feed(&mut self, page: usize) -> Result<Vec<super::MusicUnit>, ReturnAction> {
+        if page == 0 {
+            self.feed = Default::default();
+        }
+
+        fill_page!(self, page, self.feed, account, |source, endpoint, from| {
+            source.get_feed(&endpoint, from)
+        })
+    }
+
+    // Account can be changed from anywhere else. eg: web interface. So unlike other lists that
+    // are fetched at once, subscriptions and playlists of account are fetched again for every page
+    pub async fn get_subscriptions(
+        &mut self,
+        page: usize,
+    ) -> Result<Vec<super::ArtistUnit>, ReturnAction> {
+        let subscriptions = dispatch!(self, 0, account, |source, endpoint| source
+            .get_subscriptions(&endpoint))?;
+        page_of(subscriptions, page, self.item_per_page)
+    }
+
+    pub async fn get_account_playlists(
+        &mut self,
+        page: usize,
+    ) -> Result<Vec<super::PlaylistUnit>, ReturnAction> {
+        let playlists = self.get_all_account_playlists().await?;
+        page_of(playlists, page, self.item_per_page)
+    }
+
+    // Every playlist of the account at once. eg: to pick the one to add music to
+    pub async fn get_all_account_playlists(
+        &mut self,
+    ) -> Result<Vec<super::PlaylistUnit>, ReturnAction> {
+        dispatch!(self, 0, account, |source, endpoint| source
+            .get_account_playlists(&endpoint))
+    }
+
+    // Content of playlist of the account. Same as subscriptions this is fetched for every page
+    pub async fn get_account_playlist_content(
+        &mut self,
+        playlist_id: &str,
+        page: usize,
+    ) -> Result<Vec<super::MusicUnit>, ReturnAction> {
+        let content = dispatch!(self, 0, account, |source, endpoint| source
+            .get_account_playlist_content(&endpoint, playlist_id))?;
+        page_of(content, page, self.item_per_page)
+    }
+
+    pub async fn create_playlist(
+        &mut self,
+        name: &str,
+    ) -> Result<super::PlaylistUnit, ReturnAction> {
+        dispatch!(self, 0, account, |source, endpoint| source
+            .create_playlist(&endpoint, name))
+    }
+
+    pub async fn add_to_playlist(
+        &mut self,
+        playlist_id: &str,
+        music_id: &str,
+    ) -> Result<(), ReturnAction> {
+        dispatch!(self, 0, account, |source, endpoint| source.add_to_playlist(
+            &endpoint,
+            playlist_id,
+            music_id
+        ))
+    }
+
+    pub async fn remove_from_playlist(
+        &mut self,
+        playlist_id: &str,
+        music_id: &str,
+    ) -> Result<(), ReturnAction> {
+        dispatch!(self, 0, account, |source, endpoint| source
+            .remove_from_playlist(&endpoint, playlist_id, music_id))
+    }
+}
+
+// Items of local `page` among every item fetched at once. ReturnAction::EOR if there is nothing
+// in that page
+fn page_of<T>(
+    mut items: Vec<T>,
+    page: usize,
+    item_per_page: usize,
+) -> Result<Vec<T>, ReturnAction> {
+    let lower_limit = page * item_per_page;
+    if lower_limit >= items.len() {
+        return Err(ReturnAction::EOR);
+    }
+
+    items.truncate(lower_limit + item_per_page);
+    let mut res = items.split_off(lower_limit);
+    res.shrink_to_fit();
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::TrendingCategory;
+
+    #[tokio::test]
+    async fn built_fetchers_are_independent() {
+        let storage = in_memory_storage();
+        storage
+            .lock()
+            .unwrap()
+            .execute(
+                &format!(
+                    "INSERT INTO {tb_name} (id, title, author, duration) VALUES ('id', 'name', 'artist', '3:05')",
+                    tb_name = TB_FAVOURATES_MUSIC
+                ),
+                [],
+            )
+            .unwrap();
+
+        let mut first = FetcherBuilder::default()
+            .servers(Vec::new())
+            .region("US")
+            .storage(Storage::clone(&storage))
+            .build();
+        let mut second = FetcherBuilder::default()
+            .servers(Vec::new())
+            .region("US")
+            .build();
+
+        first.set_region("IN");
+        assert_eq!(first.region(), "IN");
+        assert_eq!(second.region(), "US");
+
+        let favourates = first.get_favourates_music(0).await.unwrap();
+        assert_eq!(favourates[0].duration, Duration::from_secs(185));
+        assert!(matches!(
+            second.get_favourates_music(0).await,
+            Err(ReturnAction::EOR)
+        ));
+
+        assert!(matches!(
+            second.get_trending_music(TrendingCategory::Music, 0).await,
+            Err(ReturnAction::Failed(FetchError::NoServer))
+        ));
+    }
+
+    #[test]
+    fn shared_fetchers_avoid_same_dead_server() {
+        let servers = ["https://one.invalid/api/v1", "https://two.invalid/api/v1"]
+            .iter()
+            .map(|url| config::Server {
+                url: url.to_string(),
+                api: ApiFlavour::Invidious,
+            })
+            .collect::<Vec<_>>();
+        let mut first = FetcherBuilder::default().servers(servers).build();
+        let second = FetcherBuilder::default().share_with(&first).build();
+        let independent = FetcherBuilder::default()
+            .servers(first.servers.clone())
+            .build();
+
+        let dead = first.pick_server(None, false, &[]).unwrap();
+        first.record_failure(dead);
+        assert_ne!(second.pick_server(None, false, &[]), Some(dead));
+        assert_eq!(second.health.lock().unwrap()[dead].failures, 1);
+        assert_eq!(independent.health.lock().unwrap()[dead].failures, 0);
+
+        // Same number of other servers given after share_with() do not get the shared health
+        let replaced = FetcherBuilder::default()
+            .share_with(&first)
+            .servers(vec![
+                config::Server {
+                    url: "https://three.invalid/api/v1".to_string(),
+                    api: ApiFlavour::Invidious,
+                },
+                config::Server {
+                    url: "https://four.invalid/api/v1".to_string(),
+                    api: ApiFlavour::Invidious,
+                },
+            ])
+            .build();
+        assert_eq!(replaced.health.lock().unwrap()[dead].failures, 0);
+    }
+
+    #[tokio::test]
+    async fn replay_recorded_fixtures() {
+        let mut fetcher = FetcherBuilder::default()
+            .servers(vec![config::Server {
+                url: "https://invidious.invalid/api/v1".to_string(),
+                api: ApiFlavour::Invidious,
+            }])
+            .fixtures(config::Fixtures {
+                mode: config::FixtureMode::Replay,
+                dir: concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../front-end/src/test-data/fixtures"
+                )
+                .to_string(),
+            })
+            .build();
+
+        let trending = fetcher
+            .get_trending_music(TrendingCategory::Music, 0)
+            .await
+            .unwrap();
+        assert_eq!(trending.len(), 10);
+        assert_eq!(trending[0].name, "University of the Sacred Heart Tokyo");
+        assert_eq!(trending[0].duration, Duration::from_secs(338));
+
+        assert!(matches!(
+            fetcher
+                .get_trending_music(TrendingCategory::Gaming, 0)
+                .await,
+            Err(ReturnAction::Failed(FetchError::NotRecorded { .. }))
+        ));
+        // Missing fixture is not the fault of server
+        assert_eq!(fetcher.health.lock().unwrap()[0].failures, 0);
     }
 }
@@ -1,4 +1,5 @@
 use config::initilize::CONFIG;
+use fetcher::ExtendClientBuilder;
 use reqwest;
 use serde::{self, Deserialize};
 use tokio;
@@ -58,12 +59,27 @@ __   ___         _                           _
         match self.sub_command.trim() {
             "run" => {
                 self.initialize_globals();
-                should_continue = true;
+                should_continue = self.confirm_network();
             }
             "update" => self.update(),
 
             "help" => self.show_help(),
 
+            "login" => match (self.arguments.first(), self.arguments.get(1)) {
+                (Some(server), Some(token)) => self.login(server, token),
+                _ => self.show_help(),
+            },
+
+            "logout" => match self.arguments.first() {
+                Some(server) => self.logout(server),
+                _ => self.show_help(),
+            },
+
+            "servers" => match self.arguments.first().map(String::as_str) {
+                Some("refresh") => self.refresh_servers(self.arguments.get(1)),
+                _ => self.show_help(),
+            },
+
             "delete" => match &self.arguments.first() {
                 Some(arg) if *arg == &String::from("config") => self.delete_config(),
                 Some(arg) if *arg == &String::from("db") => self.delete_db(),
@@ -87,6 +103,28 @@ __   ___         _                           _
         should_continue
     }
 
+    // Fixtures selected with `run --record [dir]` or `run --replay [dir]`. When dir is not given
+    // the one in config is used. None if neither flag is passed
+    pub fn fixtures(&self) -> Option<config::Fixtures> {
+        if self.sub_command.trim() != "run" {
+            return None;
+        }
+        let mode = match self.arguments.first()?.as_str() {
+            "--record" => config::FixtureMode::Record,
+            "--replay" => config::FixtureMode::Replay,
+            _ => return None,
+        };
+
+        self.initialize_globals();
+        Some(config::Fixtures {
+            mode,
+            dir: match self.arguments.get(1) {
+                Some(dir) => dir.clone(),
+                None => CONFIG.fixtures.dir.clone(),
+            },
+        })
+    }
+
     pub fn show_version(self) {
         let prog_name = env!("CARGO_PKG_NAME", "ytui_music");
         let version = env!("CARGO_PKG_VERSION", "undefined");
@@ -125,6 +163,11 @@ __   ___         _                           _
             quit = keys.quit,
             v_inc = keys.vol_increase,
             v_dec = keys.vol_decrease,
+            radio = keys.radio,
+            auto = keys.autoplay,
+            lyrics = keys.lyrics,
+            pl_add = keys.playlist_add,
+            pl_rm = keys.playlist_remove,
         );
     }
 
@@ -132,6 +175,233 @@ __   ___         _                           _
         println!(include_str!("help_message.txt"));
     }
 
+    // Url of server in config that is same as given one. Only invidious servers have accounts
+    fn account_server(&self, url: &str) -> Option<&'static str> {
+        self.initialize_globals();
+        let server = CONFIG
+            .servers
+            .list
+            .iter()
+            .find(|server| server.url.trim_end_matches('/') == url.trim_end_matches('/'));
+
+        match server {
+            Some(server) if server.api == config::ApiFlavour::Invidious => Some(&server.url),
+            Some(_) => {
+                eprintln!(
+                    "{url} is not an invidious server. Only invidious accounts are supported",
+                    url = url
+                );
+                None
+            }
+            None => {
+                eprintln!("{url} is not in the server list of config. Add it to `list` of `Servers` first", url = url);
+                None
+            }
+        }
+    }
+
+    pub fn login(&self, server: &str, token: &str) {
+        let server = match self.account_server(server) {
+            Some(server) => server,
+            None => return,
+        };
+
+        match fetcher::account::save(&config::initilize::STORAGE, server, token.trim()) {
+            Ok(()) => println!("Logged in to {server}. Feed, subscriptions and playlists of the account are shown from next run", server = server),
+            Err(err) => eprintln!("Cannot save the token. Error: {err}", err = err),
+        }
+    }
+
+    pub fn logout(&self, server: &str) {
+        let server = match self.account_server(server) {
+            Some(server) => server,
+            None => return,
+        };
+
+        match fetcher::account::remove(&config::initilize::STORAGE, server) {
+            Ok(true) => println!("Logged out from {server}", server = server),
+            Ok(false) => eprintln!("{server} was never logged in to", server = server),
+            Err(err) => eprintln!("Cannot remove the token. Error: {err}", err = err),
+        }
+    }
+
+    // Refresh server list before starting when `refresh_after_days` of `Servers` have passed
+    // since last refresh. This is done before CONFIG is loaded so that new list is used right away
+    pub fn refresh_servers_if_due(&self) {
+        if self.sub_command.trim() != "run"
+            || self.arguments.first().map(String::as_str) == Some("--replay")
+        {
+            return;
+        }
+        let config = match config::ConfigContainer::give_me_config() {
+            Some(container) => container.config,
+            None => return,
+        };
+        // No request is sent while replaying
+        if config.fixtures.mode == config::FixtureMode::Replay
+            || !config.servers.is_refresh_due(fetcher::health::now())
+        {
+            return;
+        }
+
+        println!(
+            "Server list was refreshed more than {days} days ago. Refreshing...",
+            days = config.servers.refresh_after_days
+        );
+        // Current list is used as is until next attempt instead of trying again on every run
+        let attempted = config::Servers {
+            list: config.servers.list.clone(),
+            refresh_after_days: config.servers.refresh_after_days,
+            refreshed_at: config.servers.refreshed_at,
+            attempted_at: fetcher::health::now(),
+        };
+        if !Self::refresh_server_list(config, None) {
+            config::ConfigContainer::save_servers(&attempted);
+        }
+    }
+
+    pub fn refresh_servers(&self, from: Option<&String>) {
+        match config::ConfigContainer::give_me_config() {
+            Some(container) => {
+                Self::refresh_server_list(container.config, from);
+            }
+            None => eprintln!("Cannot read config. Server list is not refreshed"),
+        }
+    }
+
+    // Replace invidious servers in config with public instances that are working right now.
+    // Instance list is downloaded unless a file of it is given. Other servers are kept as is.
+    // Returns whether the new list was saved
+    fn refresh_server_list(config: config::Config, from: Option<&String>) -> bool {
+        // Same timeout as the requests to the servers so that startup is not held forever by a
+        // hung download
+        let timeout = std::time::Duration::from_millis(config.constants.server_time_out as u64);
+        let client = match reqwest::ClientBuilder::new()
+            .connect_timeout(timeout)
+            .timeout(timeout)
+            .with_network(&config.network)
+            .build()
+        {
+            Ok(client) => client,
+            Err(err) => {
+                eprintln!("Cannot build reqwest client. Error: {err}", err = err);
+                return false;
+            }
+        };
+
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Cannot build tokio runtime to refresh servers")
+            .block_on(async move {
+                let body = match from {
+                    Some(path) => match std::fs::read(path) {
+                        Ok(body) => body,
+                        Err(err) => {
+                            eprintln!(
+                                "Cannot read instance list from {path}. Error: {err}",
+                                path = path,
+                                err = err
+                            );
+                            return false;
+                        }
+                    },
+                    None => {
+                        let url = fetcher::instances::INSTANCES_URL;
+                        println!("Downloading instance list from {url}", url = url);
+                        let res = match client.get(url).send().await {
+                            Ok(res) => res.error_for_status(),
+                            Err(err) => Err(err),
+                        };
+                        match res {
+                            Ok(res) => match res.bytes().await {
+                                Ok(body) => body.to_vec(),
+                                Err(err) => {
+                                    eprintln!("Cannot read instance list. Error: {err}", err = err);
+                                    return false;
+                                }
+                            },
+                            Err(err) => {
+                                eprintln!("Cannot download instance list. Error: {err}", err = err);
+                                return false;
+                            }
+                        }
+                    }
+                };
+
+                let candidates = match fetcher::instances::candidates(&body) {
+                    Ok(candidates) => candidates,
+                    Err(err) => {
+                        eprintln!("Instance list is not in expected format. Error: {err}", err = err);
+                        return false;
+                    }
+                };
+                println!(
+                    "Checking {count} healthy instances with api. Please wait...",
+                    count = candidates.len()
+                );
+                let working = fetcher::instances::probe(&client, candidates).await;
+                if working.is_empty() {
+                    eprintln!("None of the instances are working right now. Server list is kept as is");
+                    return false;
+                }
+
+                let count = working.len();
+                // Own instance and the one logged in to are kept even if they are not working now
+                let listed = fetcher::instances::listed(&body).unwrap_or_default();
+                let tokens = fetcher::account::load(
+                    &config::initilize::STORAGE,
+                    config.servers.list.iter().map(|server| &server.url),
+                );
+                let list =
+                    fetcher::instances::merge(config.servers.list, &tokens, &listed, working);
+                let servers = config::Servers {
+                    list,
+                    refresh_after_days: config.servers.refresh_after_days,
+                    refreshed_at: fetcher::health::now(),
+                    attempted_at: fetcher::health::now(),
+                };
+
+                match config::ConfigContainer::save_servers(&servers) {
+                    Some(backup) => {
+                        println!(
+                            "Saved {count} working public invidious servers to config along with your own and logged in ones. Previous config is kept in {backup}",
+                            count = count,
+                            backup = backup.to_string_lossy()
+                        );
+                        true
+                    }
+                    None => {
+                        eprintln!("Server list is not saved due to previous error");
+                        false
+                    }
+                }
+            })
+    }
+
+    // mpv cannot stream through socks proxy. With only `socks_proxy` set everything else would
+    // go through the proxy but the music itself would be streamed directly. So ask before that
+    fn confirm_network(&self) -> bool {
+        let network = &CONFIG.network;
+        if network.socks_proxy.trim().is_empty() || !network.http_proxy.trim().is_empty() {
+            return true;
+        }
+
+        eprintln!("WARNING: Only `socks_proxy` is set in `Network` of config. mpv do not support socks proxy so music will be streamed WITHOUT the proxy.");
+        eprintln!(
+            "Set `http_proxy` as well to stream through the proxy. Continue anyway? [yes/no]"
+        );
+        let mut response = String::new();
+        std::io::stdin().read_line(&mut response).unwrap();
+        match response.trim().to_ascii_lowercase().as_str() {
+            "yes" | "y" => true,
+            _ => {
+                eprintln!("Exiting..");
+                false
+            }
+        }
+    }
+
     pub fn initialize_globals(&self) {
         lazy_static::initialize(&config::initilize::INIT);
     }
@@ -228,6 +498,7 @@ __   ___         _                           _
 
         let client_json = reqwest::ClientBuilder::new()
             .default_headers(headers.clone())
+            .with_network(&CONFIG.network)
             .build()
             .expect("Cannot build json accepting reqwest client.");
 
@@ -269,6 +540,7 @@ __   ___         _                           _
         );
         let client_octet = reqwest::ClientBuilder::new()
             .default_headers(headers)
+            .with_network(&CONFIG.network)
             .build()
             .expect("Cannot build octet accepting reqwest client.");
 
@@ -2,13 +2,28 @@ use crate::ui::{
     self,
     event::{MIDDLE_ARTIST_INDEX, MIDDLE_MUSIC_INDEX, MIDDLE_PLAYLIST_INDEX},
 };
+use config::initilize::CONFIG;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+// How often to check if the request being waited for is still needed. See unless_stale()
+const STALE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+// Search suggestions are only fetched once user stop typing in searchbar for this long
+const SUGGESTION_DEBOUNCE: Duration = Duration::from_millis(300);
 
 macro_rules! handle_response {
-    ($response: expr, $state_original: expr, $win_index: expr, $target: ident) => {{
+    ($response: expr, $state_original: expr, $win_index: expr, $target: ident, $window: expr, [$($after: expr),*]) => {{
         let mut state = $state_original.lock().unwrap();
         // return the boolean which is only truw when response is RETRY
         let mut need_retry = false;
+        // $window is made active after the response is handled unless error is to be shown in popup
+        // Bars are fetched concurrently, so the window of bar that used to be filled after this
+        // one ($after) is kept active no matter which response arrives last
+        if ![$($after),*].contains(&state.active) {
+            state.active = $window;
+        }
         match $response {
             Ok(mut data) => {
                 state.status = "Success..";
@@ -17,8 +32,10 @@ macro_rules! handle_response {
             }
             Err(e) => {
                 match e {
-                    fetcher::ReturnAction::Failed => {
-                        state.status = "Fetch error..";
+                    fetcher::ReturnAction::Failed(err) => {
+                        // short reason in status bar and full message with what to do in popup
+                        state.status = err.short();
+                        state.active = ui::Window::Popup("Fetch error", err.to_string());
                     }
                     fetcher::ReturnAction::EOR => {
                         state.status = "Result end..";
@@ -55,11 +72,150 @@ macro_rules! handle_response {
     }};
 }
 
+// Wait for the `request` to complete but give up as soon as `is_stale` returns true for the
+// state. eg: when user changed the source of the bar that this request was going to fill.
+// Returns None when given up and the request is dropped along with all its pending network
+// request so that the stale response never overwrites the new view.
+async fn unless_stale<T>(
+    request: impl Future<Output = T>,
+    state_original: &Mutex<ui::State<'_>>,
+    is_stale: impl Fn(&ui::State) -> bool,
+) -> Option<T> {
+    let staled = async {
+        loop {
+            tokio::time::sleep(STALE_CHECK_INTERVAL).await;
+            let state = state_original.lock().unwrap();
+            // also give up when app is quitting so that we do not hang on exit
+            if is_stale(&state) || state.active == ui::Window::None {
+                break;
+            }
+        }
+    };
+
+    tokio::select! {
+        res = request => Some(res),
+        _ = staled => None,
+    }
+}
+
+// Fetch the $page of a bar in its own task so that the other bars are not held up.
+// Any request of same bar still in flight is aborted first. $fetch is run with the fetcher of
+// this bar as $fetcher and the page to fetch as $page_of and the response is handled with
+// handle_response! unless the bar have moved to another source meanwhile as told by $is_stale
+// for the $state.
+// Once shown, next page is also fetched in background when $prefetch is true. Fetcher keeps
+// what it fetched so the next page is then served without waiting for server
+macro_rules! fill_bar {
+    ($bar: expr, $state_original: expr, $notifier: expr, $region: expr, $page: expr, $same_source: expr, $prefetch: expr, |$fetcher: ident, $page_of: ident| $fetch: expr, |$state: ident| $is_stale: expr, $win_index: expr, $target: ident, $window: expr, [$($after: expr),*]) => {{
+        if let Some(task) = $bar.task.take() {
+            // Next page of same source is most likely what is being asked now. So the prefetch
+            // is left to complete and this request waits for it
+            if !($same_source && $bar.prefetching.load(Ordering::Relaxed)) {
+                task.abort();
+            }
+        }
+        $bar.prefetching.store(false, Ordering::Relaxed);
+        let fetcher = Arc::clone(&$bar.fetcher);
+        let state_original = Arc::clone($state_original);
+        let notifier = Arc::clone($notifier);
+        let retry = Arc::clone(&$bar.retry);
+        let prefetching = Arc::clone(&$bar.prefetching);
+        let region: String = $region;
+        let page: usize = $page;
+        let prefetch = $prefetch && CONFIG.constants.prefetch_next_page;
+
+        $bar.task = Some(tokio::spawn(async move {
+            let mut $fetcher = fetcher.lock().await;
+            if $fetcher.region() != region {
+                $fetcher.set_region(&region);
+            }
+            let is_stale = |$state: &ui::State| $is_stale;
+            let response = {
+                let $page_of = page;
+                unless_stale($fetch, &state_original, &is_stale).await
+            };
+
+            let mut shown = false;
+            let need_retry = match response {
+                Some(response) if !is_stale(&state_original.lock().unwrap()) => {
+                    shown = response.is_ok();
+                    handle_response!(
+                        response,
+                        state_original,
+                        $win_index,
+                        $target,
+                        $window,
+                        [$($after),*]
+                    )
+                }
+                _ => false,
+            };
+            // Items the server sent broken were fixed or left out. The page is still shown but
+            // say so instead of plain success
+            let problems = $fetcher.take_problems();
+            if shown && !problems.is_empty() {
+                let mut state = state_original.lock().unwrap();
+                if state.status == "Success.." {
+                    state.status = "Some items broken..";
+                }
+            }
+            retry.store(need_retry, Ordering::Relaxed);
+            // Communicator may also be waiting for the notification now so notify_one may not
+            // reach the painter
+            notifier.notify_all();
+
+            // Response is not needed. Error is also ignored as it is shown if user actually goes
+            // to that page
+            if prefetch && shown {
+                prefetching.store(true, Ordering::Relaxed);
+                let $page_of = page + 1;
+                let _ = $fetch.await;
+                prefetching.store(false, Ordering::Relaxed);
+            }
+        }));
+    }};
+}
+
+// Each of the music, playlist and artist bar have its own fetcher so that all of them can be
+// fetched at once. Fetcher is behind async mutex so that request of a bar waits for the
+// previous request of same bar to be aborted or to finish prefetching before using it.
+struct Bar {
+    fetcher: Arc<tokio::sync::Mutex<fetcher::Fetcher>>,
+    // Request being fetched in background
+    task: Option<tokio::task::JoinHandle<()>>,
+    // Set by the task when response was ReturnAction::Retry
+    retry: Arc<AtomicBool>,
+    // Set while the task is fetching next page of what it has shown. See fill_bar!
+    prefetching: Arc<AtomicBool>,
+}
+
 pub async fn communicator<'st, 'nt>(
-    state_original: &'st mut Arc<Mutex<ui::State<'_>>>,
+    state_original: &'st mut Arc<Mutex<ui::State<'static>>>,
     notifier: &'nt mut Arc<Condvar>,
+    fixtures: Option<config::Fixtures>,
 ) {
-    let mut fetcher = fetcher::Fetcher::default();
+    // fixtures passed from command line take precedence over the one in config
+    let builder = || match fixtures {
+        Some(ref fixtures) => fetcher::FetcherBuilder::from_config().fixtures(fixtures.clone()),
+        None => fetcher::FetcherBuilder::from_config(),
+    };
+    // This one is for everything other than the bars. Every bar share the server health with it
+    let mut fetcher = builder().build();
+    // Indexed same as need_retry. See MIDDLE_*_INDEX
+    let mut bars: [Bar; 3] = std::array::from_fn(|_| Bar {
+        fetcher: Arc::new(tokio::sync::Mutex::new(
+            builder().share_with(&fetcher).build(),
+        )),
+        task: None,
+        retry: Default::default(),
+        prefetching: Default::default(),
+    });
+    // Suggestions are fetched in background like the bars so that typing in searchbar does not
+    // hold up the rest. Only the latest query is fetched and the task of previous one is aborted
+    let suggestion_fetcher = Arc::new(tokio::sync::Mutex::new(
+        builder().share_with(&fetcher).build(),
+    ));
+    let mut suggestion_task: Option<tokio::task::JoinHandle<()>> = None;
 
     // variables with prev_ suffex are to be compared with respective current variables from state.
     // This is to check weather anything have changed from previous data request from user so that
@@ -77,14 +233,36 @@ pub async fn communicator<'st, 'nt>(
     let mut prev_music_page: Option<usize> = None;
     let mut prev_playlist_page: Option<usize> = None;
     let mut prev_artist_page: Option<usize> = None;
+    // music whose related music were last queued by autoplay
+    let mut prev_autoplay_seed = String::new();
+    // query in searchbar for which suggestions were last fetched
+    let mut prev_suggestion_query = String::new();
     // set these booleans to true when request handeling failed with RETREY response. if this is
     // true then other condition should not have to be true
     let mut need_retry = [false; 3];
+    // set to true when a request was dropped because it was stale. The notification that made it
+    // stale was sent while we were not waiting for it so next iteration should not wait
+    let mut dropped_stale = false;
 
     'communicator_loop: loop {
-        let mut state = notifier.wait(state_original.lock().unwrap()).unwrap();
-        if state.active == ui::Window::None {
-            break 'communicator_loop;
+        {
+            let state = if dropped_stale {
+                state_original.lock().unwrap()
+            } else {
+                notifier.wait(state_original.lock().unwrap()).unwrap()
+            };
+            dropped_stale = false;
+            if state.active == ui::Window::None {
+                break 'communicator_loop;
+            }
+        }
+
+        // Response of bars is shown by the task itself. Only whether they should be retried is
+        // left to know
+        for (index, bar) in bars.iter().enumerate() {
+            if bar.retry.swap(false, Ordering::Relaxed) {
+                need_retry[index] = true;
+            }
         }
 
         // This block is executed when the source of playlist has changed from previous iteration
@@ -100,166 +278,535 @@ pub async fn communicator<'st, 'nt>(
         //    added to ensure that it is requesting at least Some page not nothing. eg: when EOR is
         //    reached fetched_page is set to None and for None there is nothing to fetch. See EOR
         //    condition in handle_response! macro
+        // Request is then sent in background (see fill_bar!) and this loop moves on right away
+        // so that all the bars are fetched at once
         // UGH!! this if statement condition check is too ugly. I hate it
-        if state.filled_source.1 != prev_playlistbar_source
-            || need_retry[MIDDLE_PLAYLIST_INDEX]
-            || (state.fetched_page[MIDDLE_PLAYLIST_INDEX] != prev_playlist_page
-                && state.fetched_page[MIDDLE_PLAYLIST_INDEX].is_some())
+        // Each bar is checked in its own scope so that state is always unlocked at the end of it
         {
-            // clear the target so that noone gets confused if it the response from previous or
-            // current request
-            state.playlistbar.0.clear();
-            state.status = "Fetch playlist..";
+            let mut state = state_original.lock().unwrap();
+            if state.filled_source.1 != prev_playlistbar_source
+                || need_retry[MIDDLE_PLAYLIST_INDEX]
+                || (state.fetched_page[MIDDLE_PLAYLIST_INDEX] != prev_playlist_page
+                    && state.fetched_page[MIDDLE_PLAYLIST_INDEX].is_some())
+            {
+                // clear the target so that noone gets confused if it the response from previous or
+                // current request
+                state.playlistbar.0.clear();
+                state.status = "Fetch playlist..";
 
-            notifier.notify_one();
+                notifier.notify_one();
 
-            // condition of if made sure that fetched_page[MIDDLE_PLAYLIST_INDEX] is Some vlaue so
-            // unwrapping it is safe.
-            let page = state.fetched_page[MIDDLE_PLAYLIST_INDEX].unwrap();
+                // condition of if made sure that fetched_page[MIDDLE_PLAYLIST_INDEX] is Some vlaue so
+                // unwrapping it is safe.
+                let page = state.fetched_page[MIDDLE_PLAYLIST_INDEX].unwrap();
+                let same_source = state.filled_source.1 == prev_playlistbar_source;
 
-            // Save this source as previous source for next iteration
-            prev_playlistbar_source = state.filled_source.1.clone();
-            prev_playlist_page = Some(page);
+                // Save this source as previous source for next iteration
+                prev_playlistbar_source = state.filled_source.1.clone();
+                prev_playlist_page = Some(page);
+                need_retry[MIDDLE_PLAYLIST_INDEX] = false;
+                let region = state.region.0.clone();
 
-            // early drop the state so ui is not blocked while this thread send web req. See: else
-            // block documentation
-            std::mem::drop(state);
+                // early drop the state so ui is not blocked. See: else block documentation
+                std::mem::drop(state);
 
-            // This is the variable from which the response from matching source is set and later
-            // handled with handle_response! macro
-            let playlist_content;
+                let source = prev_playlistbar_source.clone();
+                fill_bar!(
+                    bars[MIDDLE_PLAYLIST_INDEX],
+                    state_original,
+                    notifier,
+                    region,
+                    page,
+                    same_source,
+                    matches!(
+                        source,
+                        ui::PlaylistbarSource::Search(..) | ui::PlaylistbarSource::Artist(_)
+                    ),
+                    |fetcher, page| async {
+                        match source {
+                            ui::PlaylistbarSource::Search(ref term, ref options) => {
+                                fetcher.search_playlist(term, options, page).await
+                            }
+                            ui::PlaylistbarSource::Artist(ref artist_id) => {
+                                fetcher.get_playlist_of_channel(artist_id, page).await
+                            }
+                            ui::PlaylistbarSource::Favourates => {
+                                fetcher.get_favourates_playlist(page).await
+                            }
+                            ui::PlaylistbarSource::Account => {
+                                fetcher.get_account_playlists(page).await
+                            }
+                            ui::PlaylistbarSource::RecentlyPlayed => {
+                                // TODO
+                                Ok(Vec::new())
+                            }
+                        }
+                    },
+                    |state| state.filled_source.1 != source,
+                    MIDDLE_PLAYLIST_INDEX,
+                    playlistbar,
+                    ui::Window::Playlistbar,
+                    [ui::Window::Artistbar, ui::Window::Musicbar]
+                );
+            }
+        }
 
-            // At this point state.filled.source.1 and prev_playlistbar_source is same. As state is
-            // already dropped we cant match state.filled.source.1 so match this
-            match prev_playlistbar_source {
-                ui::PlaylistbarSource::Search(ref term) => {
-                    playlist_content = fetcher.search_playlist(term, page).await;
-                }
-                ui::PlaylistbarSource::Artist(ref artist_id) => {
-                    playlist_content = fetcher.get_playlist_of_channel(artist_id, page).await;
-                }
-                ui::PlaylistbarSource::Favourates => {
-                    playlist_content = fetcher.get_favourates_playlist(page).await;
-                }
-                ui::PlaylistbarSource::RecentlyPlayed => {
-                    // TODO
-                    playlist_content = Ok(Vec::new());
+        // Checks and fills the artistbar.
+        {
+            let mut state = state_original.lock().unwrap();
+            if state.filled_source.2 != prev_artistbar_source
+                || need_retry[MIDDLE_ARTIST_INDEX]
+                || (state.fetched_page[MIDDLE_ARTIST_INDEX] != prev_artist_page
+                    && state.fetched_page[MIDDLE_ARTIST_INDEX].is_some())
+            {
+                state.artistbar.0.clear();
+                state.status = "Fetch artists..";
+                notifier.notify_one();
+
+                let page = state.fetched_page[MIDDLE_ARTIST_INDEX].unwrap();
+                let same_source = state.filled_source.2 == prev_artistbar_source;
+                prev_artistbar_source = state.filled_source.2.clone();
+                prev_artist_page = Some(page);
+                need_retry[MIDDLE_ARTIST_INDEX] = false;
+                let region = state.region.0.clone();
+                std::mem::drop(state);
+
+                let source = prev_artistbar_source.clone();
+                fill_bar!(
+                    bars[MIDDLE_ARTIST_INDEX],
+                    state_original,
+                    notifier,
+                    region,
+                    page,
+                    same_source,
+                    matches!(source, ui::ArtistbarSource::Search(..)),
+                    |fetcher, page| async {
+                        match source {
+                            ui::ArtistbarSource::Search(ref term, ref options) => {
+                                fetcher.search_artist(term, options, page).await
+                            }
+                            ui::ArtistbarSource::Favourates => {
+                                fetcher.get_favourates_artist(page).await
+                            }
+                            ui::ArtistbarSource::Subscriptions => {
+                                fetcher.get_subscriptions(page).await
+                            }
+                            ui::ArtistbarSource::RecentlyPlayed => {
+                                // TODO:
+                                Ok(Vec::new())
+                            }
+                        }
+                    },
+                    |state| state.filled_source.2 != source,
+                    MIDDLE_ARTIST_INDEX,
+                    artistbar,
+                    ui::Window::Artistbar,
+                    [ui::Window::Musicbar]
+                );
+            }
+        }
+
+        // Checks and fills the musicbar
+        {
+            let mut state = state_original.lock().unwrap();
+            // Region was picked in the tui. Forget the page shown so that trending is fetched again
+            // for that region. Fetcher of bars pick the region when they are used next
+            if state.region.0 != fetcher.region() {
+                fetcher.set_region(&state.region.0);
+                if let ui::MusicbarSource::Trending(_) = prev_musicbar_source {
+                    prev_music_page = None;
                 }
             }
+            if state.filled_source.0 != prev_musicbar_source
+                || need_retry[MIDDLE_MUSIC_INDEX]
+                || (state.fetched_page[MIDDLE_MUSIC_INDEX] != prev_music_page
+                    && state.fetched_page[MIDDLE_MUSIC_INDEX].is_some())
+            {
+                state.musicbar.0.clear();
+                state.status = "Fetch music..";
+                notifier.notify_one();
+
+                let page = state.fetched_page[MIDDLE_MUSIC_INDEX].unwrap();
+                let same_source = state.filled_source.0 == prev_musicbar_source;
+                prev_musicbar_source = state.filled_source.0.clone();
+                prev_music_page = Some(page);
+                need_retry[MIDDLE_MUSIC_INDEX] = false;
+                let region = state.region.0.clone();
+                std::mem::drop(state);
 
-            // if return action is RETRY set so in need_retry so that nex interation will try again
-            let retry = handle_response!(
-                playlist_content,
+                let source = prev_musicbar_source.clone();
+                fill_bar!(
+                    bars[MIDDLE_MUSIC_INDEX],
+                    state_original,
+                    notifier,
+                    region,
+                    page,
+                    same_source,
+                    // Favourates are read from storage and are fast enough. Radio keeps the queue
+                    // fed anyway. Account playlist is fetched whole for every page and new releases
+                    // of every artist are fetched at once
+                    matches!(
+                        source,
+                        ui::MusicbarSource::Trending(_)
+                            | ui::MusicbarSource::Search(..)
+                            | ui::MusicbarSource::Playlist(_)
+                            | ui::MusicbarSource::Artist(_)
+                            | ui::MusicbarSource::Feed
+                    ),
+                    |fetcher, page| async {
+                        match source {
+                            ui::MusicbarSource::Trending(category) => {
+                                fetcher.get_trending_music(category, page).await
+                            }
+                            ui::MusicbarSource::Search(ref term, ref options) => {
+                                fetcher.search_music(term, options, page).await
+                            }
+                            ui::MusicbarSource::Playlist(ref playlist_id) => {
+                                fetcher.get_playlist_content(playlist_id, page).await
+                            }
+                            ui::MusicbarSource::Artist(ref artist_id) => {
+                                fetcher.get_videos_of_channel(artist_id, page).await
+                            }
+                            ui::MusicbarSource::Radio(ref music_id) => {
+                                fetcher.get_radio(music_id, page).await
+                            }
+                            ui::MusicbarSource::Favourates => {
+                                fetcher.get_favourates_music(page).await
+                            }
+                            ui::MusicbarSource::Feed => fetcher.get_feed(page).await,
+                            ui::MusicbarSource::NewReleases => fetcher.get_new_releases(page).await,
+                            ui::MusicbarSource::AccountPlaylist(ref playlist_id) => {
+                                fetcher
+                                    .get_account_playlist_content(playlist_id, page)
+                                    .await
+                            }
+                            ui::MusicbarSource::RecentlyPlayed => {
+                                // TODO: handle each variant with accurate function
+                                Ok(Vec::new())
+                            }
+                        }
+                    },
+                    |state| state.filled_source.0 != source,
+                    MIDDLE_MUSIC_INDEX,
+                    musicbar,
+                    ui::Window::Musicbar,
+                    []
+                );
+            }
+        }
+
+        // Feeds the next page of playlist or radio being played to mpv when queue is about to
+        // run out
+        // Fetcher of musicbar is used as it already have the pages of playlist or radio shown
+        // there. When musicbar is being fetched this is left to the next iteration
+        let queue_request = state_original.lock().unwrap().queue_feed_wants();
+        let music_fetcher = bars[MIDDLE_MUSIC_INDEX].fetcher.try_lock();
+        if let (Some((source, page)), Ok(mut fetcher)) = (queue_request, music_fetcher) {
+            let musics = unless_stale(
+                async {
+                    match source {
+                        ui::MusicbarSource::Playlist(ref playlist_id) => {
+                            fetcher.get_playlist_content(playlist_id, page).await
+                        }
+                        ui::MusicbarSource::AccountPlaylist(ref playlist_id) => {
+                            fetcher
+                                .get_account_playlist_content(playlist_id, page)
+                                .await
+                        }
+                        ui::MusicbarSource::Radio(ref music_id) => {
+                            fetcher.get_radio(music_id, page).await
+                        }
+                        // queue is never fed from other sources
+                        _ => Err(fetcher::ReturnAction::EOR),
+                    }
+                },
                 state_original,
-                MIDDLE_PLAYLIST_INDEX,
-                playlistbar
-            );
-            need_retry[MIDDLE_PLAYLIST_INDEX] = retry;
-            state_original.lock().unwrap().active = ui::Window::Playlistbar;
+                |state| state.queue_feed.as_ref().map(|(feed, _)| feed) != Some(&source),
+            )
+            .await;
+
+            let mut state = state_original.lock().unwrap();
+            match musics {
+                // Another playlist or music was played meanwhile
+                Some(_) if state.queue_feed != Some((source, page)) => {}
+                Some(Ok(musics)) => state.queue_feed_page(&musics),
+                // Everything is queued
+                Some(Err(fetcher::ReturnAction::EOR)) => state.queue_feed = None,
+                // queue_feed is untouched so this is tried again in next iteration
+                Some(Err(fetcher::ReturnAction::Retry)) => {}
+                Some(Err(fetcher::ReturnAction::Failed(err))) => {
+                    state.status = err.short();
+                    state.queue_feed = None;
+                }
+                None => dropped_stale = true,
+            }
             notifier.notify_one();
-        } else {
-            // State is always unlocked in above block and dropped in if block. But when if block
-            // condition is not met then the state will never be unlocked so always drop the state.
-            // Note, insetad of dropping state inside and outside if statement one could think to
-            // drop it outside the if/else block so single drop statement will work. But when if
-            // block is executed web request is made and when state is dropped outside if/else
-            // block then the state will remain locked until web request is made which may even
-            // take indefinite time so which in turn keeps the ui blocked state is dropped early
-            // in if block
-            std::mem::drop(state);
         }
 
-        // Checks and fills the artistbar.
-        let mut state = state_original.lock().unwrap();
-        if state.filled_source.2 != prev_artistbar_source
-            || need_retry[MIDDLE_ARTIST_INDEX]
-            || (state.fetched_page[MIDDLE_ARTIST_INDEX] != prev_artist_page
-                && state.fetched_page[MIDDLE_ARTIST_INDEX].is_some())
-        {
-            state.artistbar.0.clear();
-            state.status = "Fetch artists..";
-            notifier.notify_one();
+        // Fills the playlist picker with the playlists of account
+        let picker_request = {
+            let state = state_original.lock().unwrap();
+            state.active == ui::Window::PlaylistPicker && state.account.playlists.is_none()
+        };
+        if picker_request {
+            let playlists = unless_stale(
+                fetcher.get_all_account_playlists(),
+                state_original,
+                |state| state.active != ui::Window::PlaylistPicker,
+            )
+            .await;
 
-            let page = state.fetched_page[MIDDLE_ARTIST_INDEX].unwrap();
-            prev_artistbar_source = state.filled_source.2.clone();
-            prev_artist_page = Some(page);
-            std::mem::drop(state);
+            let mut state = state_original.lock().unwrap();
+            match playlists {
+                Some(Ok(playlists)) => {
+                    state.status = "Success..";
+                    state.fill_playlist_picker(playlists);
+                }
+                Some(Err(fetcher::ReturnAction::Failed(err))) => {
+                    state.status = err.short();
+                    state.active = ui::Window::Popup("Fetch error", err.to_string());
+                }
+                // playlists is still None so this is tried again in next iteration
+                Some(Err(fetcher::ReturnAction::Retry)) => state.status = "Retrying..",
+                Some(Err(fetcher::ReturnAction::EOR)) => {}
+                None => dropped_stale = true,
+            }
+            notifier.notify_one();
+        }
 
-            let artist_content;
-            match prev_artistbar_source {
-                ui::ArtistbarSource::Search(ref term) => {
-                    artist_content = fetcher.search_artist(term, page).await;
+        // Sends the change made in playlists of account to the server. This is never given up
+        // as user expects it to be saved even after moving on
+        let account_action = state_original.lock().unwrap().account.pending.take();
+        if let Some(action) = account_action {
+            let saved = match action {
+                ui::AccountAction::Create(ref name, ref music_id) => {
+                    match fetcher.create_playlist(name).await {
+                        Ok(playlist) => fetcher.add_to_playlist(&playlist.id, music_id).await,
+                        Err(err) => Err(err),
+                    }
                 }
-                ui::ArtistbarSource::Favourates => {
-                    artist_content = fetcher.get_favourates_artist(page).await;
+                ui::AccountAction::Add(ref playlist_id, ref music_id) => {
+                    fetcher.add_to_playlist(playlist_id, music_id).await
                 }
-                ui::ArtistbarSource::RecentlyPlayed => {
-                    // TODO:
-                    artist_content = Ok(Vec::new());
+                ui::AccountAction::Remove(ref playlist_id, ref music_id) => {
+                    fetcher.remove_from_playlist(playlist_id, music_id).await
                 }
-            }
+            };
 
-            let retry = handle_response!(
-                artist_content,
-                state_original,
-                MIDDLE_ARTIST_INDEX,
-                artistbar
-            );
-            need_retry[MIDDLE_ARTIST_INDEX] = retry;
-            state_original.lock().unwrap().active = ui::Window::Artistbar;
+            let mut state = state_original.lock().unwrap();
+            match saved {
+                Ok(()) => {
+                    state.account_action_done(&action);
+                    // Number of music in playlists shown in playlistbar have changed. Forget the
+                    // page shown so that it is fetched again
+                    if prev_playlistbar_source == ui::PlaylistbarSource::Account {
+                        prev_playlist_page = None;
+                    }
+                }
+                Err(fetcher::ReturnAction::Failed(err)) => {
+                    state.status = err.short();
+                    state.active = ui::Window::Popup("Save error", err.to_string());
+                }
+                Err(_) => state.status = "Not saved..",
+            }
             notifier.notify_one();
-        } else {
-            std::mem::drop(state);
         }
 
-        // Checks and fills the musicbar
-        let mut state = state_original.lock().unwrap();
-        if state.filled_source.0 != prev_musicbar_source
-            || need_retry[MIDDLE_MUSIC_INDEX]
-            || (state.fetched_page[MIDDLE_MUSIC_INDEX] != prev_music_page
-                && state.fetched_page[MIDDLE_MUSIC_INDEX].is_some())
-        {
-            state.musicbar.0.clear();
-            state.status = "Fetch music..";
+        // Fills the details window
+        let details_request = {
+            let state = state_original.lock().unwrap();
+            if state.active == ui::Window::Details && state.details.details.is_none() {
+                Some(state.details.id.clone())
+            } else {
+                None
+            }
+        };
+        if let Some(music_id) = details_request {
+            state_original.lock().unwrap().status = "Fetch details..";
             notifier.notify_one();
 
-            let page = state.fetched_page[MIDDLE_MUSIC_INDEX].unwrap();
-            prev_musicbar_source = state.filled_source.0.clone();
-            prev_music_page = Some(page);
-            std::mem::drop(state);
-            // prev_musicbar_source and current musicbar_source are equal at this point
-            let music_content;
-            match prev_musicbar_source {
-                ui::MusicbarSource::Trending => {
-                    music_content = fetcher.get_trending_music(page).await;
-                }
-                ui::MusicbarSource::Search(ref term) => {
-                    music_content = fetcher.search_music(term, page).await;
+            let details = unless_stale(
+                fetcher.get_video_details(&music_id),
+                state_original,
+                |state| state.active != ui::Window::Details || state.details.id != music_id,
+            )
+            .await;
+
+            let mut state = state_original.lock().unwrap();
+            match details {
+                Some(Ok(details)) => {
+                    state.status = "Success..";
+                    state.fill_details(details);
                 }
-                ui::MusicbarSource::Playlist(ref playlist_id) => {
-                    music_content = fetcher.get_playlist_content(playlist_id, page).await;
+                Some(Err(fetcher::ReturnAction::Failed(err))) => {
+                    state.status = err.short();
+                    state.active = ui::Window::Popup("Fetch error", err.to_string());
                 }
-                ui::MusicbarSource::Artist(ref artist_id) => {
-                    music_content = fetcher.get_videos_of_channel(artist_id, page).await;
+                // details is still None so this is tried again in next iteration
+                Some(Err(fetcher::ReturnAction::Retry)) => state.status = "Retrying..",
+                Some(Err(fetcher::ReturnAction::EOR)) => {}
+                None => dropped_stale = true,
+            }
+            notifier.notify_one();
+        }
+
+        // Fills the lyrics window. Caption tracks of the music are fetched first and then the
+        // lines of chosen track
+        let lyrics_request = {
+            let state = state_original.lock().unwrap();
+            let lyrics = &state.lyrics;
+            if state.active != ui::Window::Lyrics || lyrics.id.is_empty() {
+                None
+            } else {
+                match (&lyrics.tracks, lyrics.chosen) {
+                    (None, _) => Some((lyrics.id.clone(), None)),
+                    (Some((tracks, _)), Some(index)) if lyrics.lines_of != Some(index) => {
+                        Some((lyrics.id.clone(), Some((index, tracks[index].clone()))))
+                    }
+                    _ => None,
                 }
-                ui::MusicbarSource::Favourates => {
-                    music_content = fetcher.get_favourates_music(page).await;
+            }
+        };
+        match lyrics_request {
+            Some((music_id, None)) => {
+                state_original.lock().unwrap().status = "Fetch captions..";
+                notifier.notify_one();
+
+                let tracks = unless_stale(
+                    fetcher.get_caption_tracks(&music_id),
+                    state_original,
+                    |state| state.active != ui::Window::Lyrics || state.lyrics.id != music_id,
+                )
+                .await;
+
+                let mut state = state_original.lock().unwrap();
+                match tracks {
+                    Some(Ok(tracks)) => {
+                        state.status = "Success..";
+                        state.fill_caption_tracks(tracks);
+                    }
+                    Some(Err(fetcher::ReturnAction::Failed(err))) => {
+                        state.status = err.short();
+                        state.active = ui::Window::Popup("Fetch error", err.to_string());
+                    }
+                    // tracks is still None so this is tried again in next iteration
+                    Some(Err(fetcher::ReturnAction::Retry)) => state.status = "Retrying..",
+                    Some(Err(fetcher::ReturnAction::EOR)) => {}
+                    None => dropped_stale = true,
                 }
-                ui::MusicbarSource::RecentlyPlayed => {
-                    // TODO: handle each variant with accurate function
-                    music_content = Ok(Vec::new());
+                notifier.notify_one();
+            }
+            Some((music_id, Some((index, track)))) => {
+                state_original.lock().unwrap().status = "Fetch lyrics..";
+                notifier.notify_one();
+
+                let lines = unless_stale(
+                    fetcher.get_captions(&music_id, &track),
+                    state_original,
+                    |state| {
+                        state.active != ui::Window::Lyrics
+                            || state.lyrics.id != music_id
+                            || state.lyrics.chosen != Some(index)
+                    },
+                )
+                .await;
+
+                let mut state = state_original.lock().unwrap();
+                match lines {
+                    Some(Ok(lines)) => {
+                        state.status = "Success..";
+                        state.fill_lyrics(index, lines);
+                    }
+                    // Go back to what was shown before so that this is not asked again
+                    Some(Err(fetcher::ReturnAction::Failed(err))) => {
+                        state.status = err.short();
+                        state.lyrics.chosen = state.lyrics.lines_of;
+                        state.active = ui::Window::Popup("Fetch error", err.to_string());
+                    }
+                    Some(Err(fetcher::ReturnAction::Retry)) => state.status = "Retrying..",
+                    Some(Err(fetcher::ReturnAction::EOR)) => {}
+                    None => dropped_stale = true,
                 }
+                notifier.notify_one();
             }
+            None => {}
+        }
 
-            let retry =
-                handle_response!(music_content, state_original, MIDDLE_MUSIC_INDEX, musicbar);
-            need_retry[MIDDLE_MUSIC_INDEX] = retry;
-            state_original.lock().unwrap().active = ui::Window::Musicbar;
+        // Queue the music related to the last one when autoplay is on and queue is about to end.
+        // Same music is never asked twice in a row so that we do not keep asking when every
+        // related music is already played
+        let autoplay_seed = state_original.lock().unwrap().autoplay_wants();
+        if let Some(music_id) = autoplay_seed.filter(|seed| *seed != prev_autoplay_seed) {
+            prev_autoplay_seed = music_id.clone();
+            let related = unless_stale(
+                fetcher.get_related_music(&music_id),
+                state_original,
+                |state| state.autoplay_wants().as_ref() != Some(&music_id),
+            )
+            .await;
+
+            let mut state = state_original.lock().unwrap();
+            match related {
+                Some(Ok(musics)) => state.queue_autoplay(&musics),
+                Some(Err(fetcher::ReturnAction::Failed(err))) => state.status = err.short(),
+                // Forget the seed so that it is asked again in next iteration
+                Some(Err(fetcher::ReturnAction::Retry)) => prev_autoplay_seed.clear(),
+                Some(Err(fetcher::ReturnAction::EOR)) => {}
+                None => dropped_stale = true,
+            }
             notifier.notify_one();
-        } else {
-            // If above if block is not executed state lock should however be released
-            // so that state can be lock again for following if block
-            std::mem::drop(state);
+        }
+
+        // Checks and fills the suggestions of searchbar
+        let suggestion_query = {
+            let mut state = state_original.lock().unwrap();
+            if state.active != ui::Window::Searchbar || state.search.0 == prev_suggestion_query {
+                None
+            } else {
+                prev_suggestion_query = state.search.0.clone();
+                if let Some(task) = suggestion_task.take() {
+                    task.abort();
+                }
+                if state.search.0.trim().is_empty() {
+                    state.suggestions.0.clear();
+                    state.suggestions.1.select(None);
+                    notifier.notify_one();
+                    None
+                } else {
+                    Some(state.search.0.clone())
+                }
+            }
+        };
+
+        if let Some(query) = suggestion_query {
+            let fetcher = Arc::clone(&suggestion_fetcher);
+            let state_original = Arc::clone(state_original);
+            let notifier = Arc::clone(notifier);
+            suggestion_task = Some(tokio::spawn(async move {
+                // Request is only sent once the query stays same for SUGGESTION_DEBOUNCE. Next
+                // keystroke aborts this task before that
+                tokio::time::sleep(SUGGESTION_DEBOUNCE).await;
+                let mut fetcher = fetcher.lock().await;
+                let is_stale = |state: &ui::State| {
+                    state.active != ui::Window::Searchbar || state.search.0 != query
+                };
+                let suggestions = unless_stale(
+                    fetcher.get_search_suggestions(query.trim()),
+                    &state_original,
+                    &is_stale,
+                )
+                .await;
+
+                // Suggestions are only nice to have. Do not bother user with popup on error
+                if let Some(Ok(suggestions)) = suggestions {
+                    let mut state = state_original.lock().unwrap();
+                    if !is_stale(&state) {
+                        state.suggestions.0 = suggestions;
+                        state.suggestions.1.select(None);
+                        notifier.notify_all();
+                    }
+                }
+            }));
         }
     }
 }
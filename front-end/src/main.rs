@@ -38,6 +38,7 @@ mod ui;
 */
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let fixtures;
     {
         let opts = cli::Options::create_from_args(std::env::args());
         match opts {
@@ -49,6 +50,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 std::process::exit(1)
             }
             Ok(opts) => {
+                opts.refresh_servers_if_due();
+                fixtures = opts.fixtures();
                 let should_continue = opts.evaluate();
                 if !should_continue {
                     std::process::exit(0)
@@ -89,6 +92,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     communicator::communicator(
                         &mut state_for_communicator,
                         &mut cvar_for_communicator,
+                        fixtures,
                     )
                     .await;
                 });
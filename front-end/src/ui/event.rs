@@ -1,6 +1,11 @@
-use crate::ui::{self, utils::ExtendMpv};
+use crate::ui::{
+    self,
+    utils::{ExtendMpv, REGIONS},
+};
 use config::initilize::{CONFIG, STORAGE};
 use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use fetcher::search::SearchOptions;
+use fetcher::source::TrendingCategory;
 use std::{
     convert::TryFrom,
     sync::{Arc, Condvar, Mutex},
@@ -203,8 +208,25 @@ pub async fn event_sender(
     let handle_esc = || {
         let mut state = state_original.lock().unwrap();
         match state.active {
+            ui::Window::Details | ui::Window::Lyrics => {
+                state.active = ui::Window::Musicbar;
+                notifier.notify_all();
+            }
+            // Region picker is opened from sidebar so go back there
+            ui::Window::Region => {
+                state.active = ui::Window::Sidebar;
+                notifier.notify_all();
+            }
+            // and playlist picker from musicbar
+            ui::Window::PlaylistPicker => {
+                state.account.new_name.clear();
+                state.active = ui::Window::Musicbar;
+                notifier.notify_all();
+            }
             ui::Window::Searchbar | ui::Window::Popup(..) => {
                 state.search.0.clear();
+                state.suggestions.0.clear();
+                state.suggestions.1.select(None);
                 drop_and_call!(state, moveto_next_window);
             }
             ui::Window::BottomControl => {
@@ -232,6 +254,12 @@ pub async fn event_sender(
         match state.active {
             ui::Window::Searchbar => {
                 state.search.0.pop();
+                // query have changed so old selection means nothing
+                state.suggestions.1.select(None);
+                notifier.notify_all();
+            }
+            ui::Window::PlaylistPicker => {
+                state.account.new_name.pop();
                 notifier.notify_all();
             }
             _ => drop_and_call!(state, moveto_prev_window),
@@ -242,10 +270,124 @@ pub async fn event_sender(
     // this will simpley push the recived character in search query term and update state
     // so can the added character becomes visible
     let handle_search_input = |ch| {
-        state_original.lock().unwrap().search.0.push(ch);
+        let mut state = state_original.lock().unwrap();
+        state.search.0.push(ch);
+        state.suggestions.1.select(None);
+        notifier.notify_all();
+    };
+
+    // Every character typed in playlist picker is the name of new playlist
+    let handle_playlist_name_input = |ch| {
+        let mut state = state_original.lock().unwrap();
+        state.account.new_name.push(ch);
+        notifier.notify_all();
+    };
+
+    // select the next or previous suggestion in dropdown below searchbar
+    let advance_suggestion = |direction: HeadTo| {
+        let mut state = state_original.lock().unwrap();
+        let next_index = match state.suggestions.1.selected() {
+            None => match direction {
+                HeadTo::Prev => state.suggestions.0.len().saturating_sub(1),
+                _ => 0,
+            },
+            Some(current) => advance_index(current, state.suggestions.0.len(), direction),
+        };
+        state.suggestions.1.select(Some(next_index));
+        notifier.notify_all();
+    };
+
+    // select the next or previous chapter in details window
+    let advance_chapter = |direction: HeadTo| {
+        let mut state = state_original.lock().unwrap();
+        if state.details.chapters.0.is_empty() {
+            return;
+        }
+        let next_index = match state.details.chapters.1.selected() {
+            None => match direction {
+                HeadTo::Prev => state.details.chapters.0.len() - 1,
+                _ => 0,
+            },
+            Some(current) => advance_index(current, state.details.chapters.0.len(), direction),
+        };
+        state.details.chapters.1.select(Some(next_index));
+        notifier.notify_all();
+    };
+
+    // highlight the next or previous region in region picker
+    let advance_region = |direction: HeadTo| {
+        let mut state = state_original.lock().unwrap();
+        let next_index = match state.region.1.selected() {
+            None => 0,
+            Some(current) => advance_index(current, REGIONS.len(), direction),
+        };
+        state.region.1.select(Some(next_index));
         notifier.notify_all();
     };
 
+    // highlight the next or previous item in playlist picker. First item is to create new playlist
+    let advance_playlist_picker = |direction: HeadTo| {
+        let mut state = state_original.lock().unwrap();
+        if let Some((ref playlists, ref mut picker)) = state.account.playlists {
+            let next_index = match picker.selected() {
+                None => 0,
+                Some(current) => advance_index(current, playlists.len() + 1, direction),
+            };
+            picker.select(Some(next_index));
+            notifier.notify_all();
+        }
+    };
+
+    // highlight the next or previous language in language selector of lyrics window
+    let advance_caption_track = |direction: HeadTo| {
+        let mut state = state_original.lock().unwrap();
+        if let Some((ref tracks, ref mut selector)) = state.lyrics.tracks {
+            if tracks.is_empty() {
+                return;
+            }
+            let next_index = match selector.selected() {
+                None => 0,
+                Some(current) => advance_index(current, tracks.len(), direction),
+            };
+            selector.select(Some(next_index));
+            notifier.notify_all();
+        }
+    };
+
+    // scroll the description in details window by a line
+    let scroll_details = |direction: HeadTo| {
+        let mut state = state_original.lock().unwrap();
+        state.details.scroll = match direction {
+            HeadTo::Next => state.details.scroll.saturating_add(1),
+            HeadTo::Prev => state.details.scroll.saturating_sub(1),
+            HeadTo::Initial => 0,
+        };
+        notifier.notify_all();
+    };
+
+    // Replace the query in searchbar with the suggestion selected in dropdown.
+    // Returns false if searchbar is not active or no suggestion is selected
+    let accept_suggestion = || -> bool {
+        let mut state = state_original.lock().unwrap();
+        if state.active != ui::Window::Searchbar {
+            return false;
+        }
+        let selected = state
+            .suggestions
+            .1
+            .selected()
+            .and_then(|index| state.suggestions.0.get(index).cloned());
+        match selected {
+            Some(suggestion) => {
+                state.search.0 = suggestion;
+                state.suggestions.1.select(None);
+                notifier.notify_all();
+                true
+            }
+            None => false,
+        }
+    };
+
     // This handler is fired when use press SEARCH_SH_KEY
     // this will move the curson to the searchbar from which user can start to type the query
     let activate_search = || {
@@ -266,6 +408,15 @@ pub async fn event_sender(
             ui::Window::Musicbar => drop_and_call!(state, advance_music_list, direction),
             ui::Window::Playlistbar => drop_and_call!(state, advance_playlist_list, direction),
             ui::Window::Artistbar => drop_and_call!(state, advance_artist_list, direction),
+            ui::Window::Searchbar if !state.suggestions.0.is_empty() => {
+                drop_and_call!(state, advance_suggestion, direction)
+            }
+            ui::Window::Details => drop_and_call!(state, scroll_details, direction),
+            ui::Window::Lyrics => drop_and_call!(state, advance_caption_track, direction),
+            ui::Window::Region => drop_and_call!(state, advance_region, direction),
+            ui::Window::PlaylistPicker => {
+                drop_and_call!(state, advance_playlist_picker, direction)
+            }
             _ => match direction {
                 HeadTo::Next => drop_and_call!(state, moveto_next_window),
                 HeadTo::Prev => drop_and_call!(state, moveto_prev_window),
@@ -284,38 +435,68 @@ pub async fn event_sender(
         }
         // When prefiexed by the string as defined in config only show the specific result type
         // respectively
+        // Filters like `dur:short` can be anywhere in the query after the prefix and are
+        // seperated from query here. See SearchOptions::parse
         else if let Some(0) = search_term.find(&CONFIG.constants.search_by_type[0]) {
-            let search_term =
-                search_term[CONFIG.constants.search_by_type[0].len() - 1..].to_string();
+            let (search_term, options) = SearchOptions::parse(
+                &search_term[CONFIG.constants.search_by_type[0].len() - 1..],
+            );
             state.fetched_page[0] = Some(0);
-            state.filled_source.0 = ui::MusicbarSource::Search(search_term);
+            state.filled_source.0 = ui::MusicbarSource::Search(search_term, options);
         } else if let Some(0) = search_term.find(&CONFIG.constants.search_by_type[1]) {
-            let search_term =
-                search_term[CONFIG.constants.search_by_type[1].len() - 1..].to_string();
+            let (search_term, options) = SearchOptions::parse(
+                &search_term[CONFIG.constants.search_by_type[1].len() - 1..],
+            );
             state.fetched_page[1] = Some(0);
-            state.filled_source.1 = ui::PlaylistbarSource::Search(search_term);
+            state.filled_source.1 = ui::PlaylistbarSource::Search(search_term, options);
         } else if let Some(0) = search_term.find(&CONFIG.constants.search_by_type[2]) {
-            let search_term =
-                search_term[&CONFIG.constants.search_by_type[2].len() - 1..].to_string();
+            let (search_term, options) = SearchOptions::parse(
+                &search_term[&CONFIG.constants.search_by_type[2].len() - 1..],
+            );
             state.fetched_page[2] = Some(0);
-            state.filled_source.2 = ui::ArtistbarSource::Search(search_term);
+            state.filled_source.2 = ui::ArtistbarSource::Search(search_term, options);
         }
         // If nothing of the prefix is defined then search for all type
         else {
-            let search_term = search_term.to_string();
+            let (search_term, options) = SearchOptions::parse(search_term);
             state.fetched_page = [Some(0); 3];
-            state.filled_source.0 = ui::MusicbarSource::Search(search_term.clone());
-            state.filled_source.1 = ui::PlaylistbarSource::Search(search_term.clone());
-            state.filled_source.2 = ui::ArtistbarSource::Search(search_term);
+            state.filled_source.0 =
+                ui::MusicbarSource::Search(search_term.clone(), options.clone());
+            state.filled_source.1 =
+                ui::PlaylistbarSource::Search(search_term.clone(), options.clone());
+            state.filled_source.2 = ui::ArtistbarSource::Search(search_term, options);
         }
+        // dropdown is not needed once search is started
+        state.suggestions.0.clear();
+        state.suggestions.1.select(None);
         notifier.notify_all();
     };
 
-    let fill_trending_music = |direction: HeadTo| {
+    let fill_trending_music = |category: TrendingCategory, direction: HeadTo| {
         let mut state = state_original.lock().unwrap();
         state.fetched_page[MIDDLE_MUSIC_INDEX] =
             Some(get_page(&state.fetched_page[MIDDLE_MUSIC_INDEX], direction));
-        state.filled_source.0 = ui::MusicbarSource::Trending;
+        state.filled_source.0 = ui::MusicbarSource::Trending(category);
+        notifier.notify_all();
+    };
+
+    // Open the region picker with the region being used highlighted
+    let activate_region_picker = || {
+        let mut state = state_original.lock().unwrap();
+        let current = REGIONS.iter().position(|(code, _)| *code == state.region.0);
+        state.region.1.select(current);
+        state.active = ui::Window::Region;
+        notifier.notify_all();
+    };
+
+    // Use the region highlighted in region picker. Trending shown in musicbar is refetched
+    // for this region by communicator
+    let pick_region = || {
+        let mut state = state_original.lock().unwrap();
+        if let Some(index) = state.region.1.selected() {
+            state.region.0 = REGIONS[index].0.to_string();
+        }
+        state.active = ui::Window::Sidebar;
         notifier.notify_all();
     };
 
@@ -350,10 +531,38 @@ pub async fn event_sender(
         notifier.notify_all();
     };
 
+    // Following of the account shows its feed in musicbar along with the subscriptions
+    let fill_subscriptions = || {
+        let mut state = state_original.lock().unwrap();
+        state.filled_source.0 = ui::MusicbarSource::Feed;
+        state.filled_source.2 = ui::ArtistbarSource::Subscriptions;
+        state.fetched_page[MIDDLE_MUSIC_INDEX] = Some(0);
+        state.fetched_page[MIDDLE_ARTIST_INDEX] = Some(0);
+        notifier.notify_all();
+    };
+
+    // Music not in seen_releases are marked as new. Fetcher remembers what it returns as seen so
+    // the seen ones are read before anything is fetched
+    let fill_new_releases = || {
+        let mut state = state_original.lock().unwrap();
+        state.seen_releases = fetcher::releases::seen(&STORAGE);
+        state.filled_source.0 = ui::MusicbarSource::NewReleases;
+        state.fetched_page[MIDDLE_MUSIC_INDEX] = Some(0);
+        notifier.notify_all();
+    };
+
+    let fill_account_playlists = || {
+        let mut state = state_original.lock().unwrap();
+        state.filled_source.1 = ui::PlaylistbarSource::Account;
+        state.fetched_page[MIDDLE_PLAYLIST_INDEX] = Some(0);
+        notifier.notify_all();
+    };
+
     let fill_music_from_playlist = |direction: HeadTo| {
         let mut state = state_original.lock().unwrap();
-        if let ui::MusicbarSource::Playlist(playlist_id) = &state.filled_source.0 {
-            state.filled_source.0 = ui::MusicbarSource::Playlist(playlist_id.to_string());
+        if let ui::MusicbarSource::Playlist(_) | ui::MusicbarSource::AccountPlaylist(_) =
+            state.filled_source.0
+        {
             state.fetched_page[MIDDLE_MUSIC_INDEX] =
                 Some(get_page(&state.fetched_page[MIDDLE_MUSIC_INDEX], direction));
             notifier.notify_all();
@@ -403,7 +612,16 @@ pub async fn event_sender(
                 // It implied to change the track
                 return drop_and_call!(state, change_track, direction);
             }
-            ui::Window::Searchbar | ui::Window::Sidebar | ui::Window::Popup(..) => {
+            ui::Window::Details => {
+                // In details window highlight next/prev chapter
+                return drop_and_call!(state, advance_chapter, direction);
+            }
+            ui::Window::Searchbar
+            | ui::Window::Sidebar
+            | ui::Window::Popup(..)
+            | ui::Window::Lyrics
+            | ui::Window::Region
+            | ui::Window::PlaylistPicker => {
                 // If none of above windows are active then nothing to navigate.
                 // Early return instead of initilizing `target_index`
                 return;
@@ -415,23 +633,30 @@ pub async fn event_sender(
         notifier.notify_all();
     };
 
+    // Live stream cannot be seeked
     let seek_forward = || {
-        state_original
-            .lock()
-            .unwrap()
-            .player
-            .seek_forward(CONFIG.constants.seek_forward_secs as f64)
-            .ok();
+        let mut state = state_original.lock().unwrap();
+        if state.bottom.live {
+            state.status = "Cannot seek live..";
+        } else {
+            state
+                .player
+                .seek_forward(CONFIG.constants.seek_forward_secs as f64)
+                .ok();
+        }
         notifier.notify_all();
     };
 
     let seek_backward = || {
-        state_original
-            .lock()
-            .unwrap()
-            .player
-            .seek_backward(CONFIG.constants.seek_backward_secs as f64)
-            .ok();
+        let mut state = state_original.lock().unwrap();
+        if state.bottom.live {
+            state.status = "Cannot seek live..";
+        } else {
+            state
+                .player
+                .seek_backward(CONFIG.constants.seek_backward_secs as f64)
+                .ok();
+        }
         notifier.notify_all();
     };
 
@@ -458,6 +683,23 @@ pub async fn event_sender(
         notifier.notify_all();
     };
 
+    // Open the lyrics window of the music being played. Close it if it is already open
+    let toggle_lyrics = || {
+        let mut state = state_original.lock().unwrap();
+        state.active = if state.active == ui::Window::Lyrics {
+            ui::Window::Musicbar
+        } else {
+            ui::Window::Lyrics
+        };
+        notifier.notify_all();
+    };
+
+    let toggle_autoplay = || {
+        let mut state = state_original.lock().unwrap();
+        state.playback_behaviour.autoplay = !state.playback_behaviour.autoplay;
+        notifier.notify_all();
+    };
+
     let toggle_play = || {
         state_original.lock().unwrap().toggle_pause();
         notifier.notify_all();
@@ -496,6 +738,9 @@ pub async fn event_sender(
             .args(&["--extract-audio", "--audio-format", &CONFIG.download.format])
             .current_dir(&CONFIG.download.path)
             .kill_on_drop(false);
+        if let Some(proxy) = CONFIG.network.proxy() {
+            command.args(["--proxy", proxy]);
+        }
 
         std::mem::drop(state);
 
@@ -520,8 +765,14 @@ pub async fn event_sender(
         let mut state = state_original.lock().unwrap();
         if let Some(selected_index) = state.playlistbar.1.selected() {
             let playlist_id = state.playlistbar.0[selected_index].id.clone();
+            // Playlist of account may be private and is only in the server of account
+            let source = if state.filled_source.1 == ui::PlaylistbarSource::Account {
+                ui::MusicbarSource::AccountPlaylist(playlist_id.clone())
+            } else {
+                ui::MusicbarSource::Playlist(playlist_id.clone())
+            };
             if play {
-                state.activate_playlist(&playlist_id);
+                state.activate_playlist(source.clone());
             } else {
                 let message = format!(
                     "Playlist url: https://youtu.be/playlist?list={}",
@@ -529,7 +780,7 @@ pub async fn event_sender(
                 );
                 state.active = ui::Window::Popup("Info!", message);
             }
-            state.filled_source.0 = ui::MusicbarSource::Playlist(playlist_id);
+            state.filled_source.0 = source;
             drop_and_call!(state, fill_music_from_playlist, HeadTo::Initial);
         }
     };
@@ -538,17 +789,39 @@ pub async fn event_sender(
         let mut state = state_original.lock().unwrap();
         if let Some(selected_index) = state.musicbar.1.selected() {
             let music_id = &state.musicbar.0[selected_index].id;
+            let music_id = music_id.clone();
             if play {
-                let music_id = music_id.clone();
                 state.play_music(&music_id);
             } else {
-                let message = format!("Music url: https://youtu.be/{}", music_id);
-                state.active = ui::Window::Popup("Info!", message);
+                state.show_details(&music_id);
                 notifier.notify_all();
             }
         }
     };
 
+    // Radio is started from the music selected in musicbar if musicbar is active, otherwise from
+    // the music being played. Radio is both played and shown in musicbar
+    let start_radio = || {
+        let mut state = state_original.lock().unwrap();
+        let selected_music = match (&state.active, state.musicbar.1.selected()) {
+            (ui::Window::Musicbar, Some(selected_index)) => state
+                .musicbar
+                .0
+                .get(selected_index)
+                .map(|music| music.id.clone()),
+            _ => None,
+        };
+        match selected_music.or_else(|| state.playing_music_id()) {
+            Some(music_id) => {
+                state.start_radio(&music_id);
+                state.filled_source.0 = ui::MusicbarSource::Radio(music_id);
+                state.fetched_page[MIDDLE_MUSIC_INDEX] = Some(0);
+            }
+            None => state.status = "Nothing to start radio from..",
+        }
+        notifier.notify_all();
+    };
+
     let change_volume = |direction: HeadTo| {
         let mut state = state_original.lock().unwrap();
 
@@ -595,8 +868,9 @@ pub async fn event_sender(
                     ui::SidebarOption::try_from(state.sidebar.selected().unwrap()).unwrap();
 
                 match side_select {
-                    ui::SidebarOption::Trending => {
-                        drop_and_call!(state, fill_trending_music, HeadTo::Initial);
+                    ui::SidebarOption::Trending(category) => {
+                        std::mem::drop(state);
+                        fill_trending_music(category, HeadTo::Initial);
                     }
                     ui::SidebarOption::YoutubeCommunity => {
                         drop_and_call!(state, fill_community_source);
@@ -604,17 +878,30 @@ pub async fn event_sender(
                     ui::SidebarOption::Liked => {
                         drop_and_call!(state, fill_fav_music, HeadTo::Initial);
                     }
+                    ui::SidebarOption::Saved if state.account.logged_in => {
+                        drop_and_call!(state, fill_account_playlists);
+                    }
                     ui::SidebarOption::Saved => {
                         drop_and_call!(state, fill_fav_playlist, HeadTo::Initial);
                     }
+                    ui::SidebarOption::Following if state.account.logged_in => {
+                        drop_and_call!(state, fill_subscriptions);
+                    }
                     ui::SidebarOption::Following => {
                         drop_and_call!(state, fill_fav_artist, HeadTo::Initial);
                     }
+                    ui::SidebarOption::NewReleases => {
+                        drop_and_call!(state, fill_new_releases);
+                    }
                     ui::SidebarOption::Search => drop_and_call!(state, activate_search),
+                    ui::SidebarOption::Region => drop_and_call!(state, activate_region_picker),
                 }
             }
             ui::Window::Searchbar => {
-                drop_and_call!(state, start_search);
+                // If some suggestion is selected search for that instead
+                std::mem::drop(state);
+                accept_suggestion();
+                start_search();
             }
 
             // On enter play the music
@@ -633,10 +920,45 @@ pub async fn event_sender(
                     fill_playlist_from_artist(HeadTo::Initial);
                 }
             }
+            // Seek to highlighted chapter
+            ui::Window::Details => {
+                state.play_chapter();
+                notifier.notify_all();
+            }
+            // Show lyrics in highlighted language
+            ui::Window::Lyrics => {
+                state.choose_caption_track();
+                notifier.notify_all();
+            }
+            ui::Window::Region => drop_and_call!(state, pick_region),
+            ui::Window::PlaylistPicker => {
+                state.pick_playlist();
+                notifier.notify_all();
+            }
             ui::Window::None | ui::Window::BottomControl | ui::Window::Popup(..) => {}
         }
     };
 
+    // In searchbar tab completes the query with selected suggestion. Otherwise (or when nothing
+    // is selected) move to next window as does right arrow
+    let handle_tab = || {
+        if !accept_suggestion() {
+            moveto_next_window();
+        }
+    };
+
+    // Add the music focused in musicbar to playlist of account or remove it from the playlist
+    // of account shown in musicbar
+    let handle_account_playlist = |add: bool| {
+        let mut state = state_original.lock().unwrap();
+        if add {
+            state.open_playlist_picker();
+        } else {
+            state.remove_from_account_playlist();
+        }
+        notifier.notify_all();
+    };
+
     let handle_favourates = |add: bool| {
         let mut state = state_original.lock().unwrap();
 
@@ -705,9 +1027,12 @@ pub async fn event_sender(
                         KeyCode::Up | KeyCode::PageUp => {
                             handle_up_down(HeadTo::Prev);
                         }
-                        KeyCode::Right | KeyCode::Tab => {
+                        KeyCode::Right => {
                             moveto_next_window();
                         }
+                        KeyCode::Tab => {
+                            handle_tab();
+                        }
                         KeyCode::Left | KeyCode::BackTab => {
                             moveto_prev_window();
                         }
@@ -722,8 +1047,12 @@ pub async fn event_sender(
                         }
                         KeyCode::Char(ch) => {
                             /* If searchbar is active register every char key as input term */
-                            if state_original.lock().unwrap().active == ui::Window::Searchbar {
+                            /* Same for playlist picker where it is the name of new playlist */
+                            let active = state_original.lock().unwrap().active.clone();
+                            if active == ui::Window::Searchbar {
                                 handle_search_input(ch);
+                            } else if active == ui::Window::PlaylistPicker {
+                                handle_playlist_name_input(ch);
                             }
                             // Now as this is not the input, call the shortcuts action if this key
                             // is defined in shortcuts
@@ -741,10 +1070,20 @@ pub async fn event_sender(
                                 seek_backward();
                             } else if ch == CONFIG.shortcut_keys.view {
                                 handle_view();
+                            } else if ch == CONFIG.shortcut_keys.autoplay {
+                                toggle_autoplay();
+                            } else if ch == CONFIG.shortcut_keys.radio {
+                                start_radio();
+                            } else if ch == CONFIG.shortcut_keys.lyrics {
+                                toggle_lyrics();
                             } else if ch == CONFIG.shortcut_keys.favourates_add {
                                 handle_favourates(true);
                             } else if ch == CONFIG.shortcut_keys.favourates_remove {
                                 handle_favourates(false);
+                            } else if ch == CONFIG.shortcut_keys.playlist_add {
+                                handle_account_playlist(true);
+                            } else if ch == CONFIG.shortcut_keys.playlist_remove {
+                                handle_account_playlist(false);
                             } else if ch == CONFIG.shortcut_keys.prev {
                                 if is_with_control {
                                     change_track(HeadTo::Prev);
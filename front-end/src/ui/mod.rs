@@ -27,6 +27,8 @@ use crossterm::{
     execute,
     terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use fetcher::search::SearchOptions;
+use fetcher::source::TrendingCategory;
 use shared_import::*;
 
 // Following several state defines the layout of the ui
@@ -118,6 +120,20 @@ pub struct BottomLayout {
     layout: Rect,
 }
 
+// ---------------------------------------
+// |                         |           |
+// | Rect (info/description) | Rect      |
+// |                         | (chapter) |
+// |                         |           |
+// ---------------------------------------
+// Details window covers the whole middle section (musicbar, playlistbar and artistbar) while
+// it is active. Left part shows the info of music followed by the description and right part
+// lists the chapters found in description.
+// Lyrics window uses the same layout with lyrics on the left and languages on the right
+pub struct DetailsLayout {
+    layout: [Rect; 2],
+}
+
 // This is what final ui looks like
 // ----------------------------------------------------------
 // |    Searchbar                           |  Statusbar    |
@@ -151,6 +167,12 @@ pub struct Position {
     pub music_info: Rect,
     pub bottom_icons: Rect,
     pub popup: Rect,
+    // Maximum area of suggestion dropdown. Only the height needed to show suggestions is used
+    pub suggestions: Rect,
+    // Lyrics window is drawn in same area as details window. Lyrics on the left and the
+    // language selector on the right
+    pub details_info: Rect,
+    pub details_chapters: Rect,
 }
 
 // This function will:
@@ -242,6 +264,93 @@ pub fn draw_ui(state: &mut Arc<Mutex<State>>, cvar: &mut Arc<Condvar>) {
                     position.bottom_icons,
                 );
 
+                if state_unlocked.active == Window::Details {
+                    // Same reason as in the table states above
+                    let chapter_state = unsafe { &mut (*state_ptr).details.chapters.1 };
+                    screen.render_widget(widgets::Clear, position.details_info);
+                    screen.render_widget(
+                        DetailsLayout::get_info(&state_unlocked),
+                        position.details_info,
+                    );
+                    screen.render_widget(widgets::Clear, position.details_chapters);
+                    screen.render_stateful_widget(
+                        DetailsLayout::get_chapters(&state_unlocked),
+                        position.details_chapters,
+                        chapter_state,
+                    );
+                }
+
+                if state_unlocked.active == Window::Lyrics {
+                    // Same reason as in the table states above
+                    let line_state = unsafe { &mut (*state_ptr).lyrics.lines.1 };
+                    screen.render_widget(widgets::Clear, position.details_info);
+                    screen.render_stateful_widget(
+                        DetailsLayout::get_lyrics(&state_unlocked),
+                        position.details_info,
+                        line_state,
+                    );
+                    screen.render_widget(widgets::Clear, position.details_chapters);
+                    let language_state = unsafe { &mut (*state_ptr).lyrics.tracks };
+                    if let Some((_, ref mut language_state)) = language_state {
+                        screen.render_stateful_widget(
+                            DetailsLayout::get_caption_languages(&state_unlocked),
+                            position.details_chapters,
+                            language_state,
+                        );
+                    } else {
+                        screen.render_widget(
+                            DetailsLayout::get_caption_languages(&state_unlocked),
+                            position.details_chapters,
+                        );
+                    }
+                }
+
+                if state_unlocked.active == Window::Region {
+                    // Same reason as in the table states above
+                    let region_state = unsafe { &mut (*state_ptr).region.1 };
+                    screen.render_widget(widgets::Clear, position.popup);
+                    screen.render_stateful_widget(
+                        SideBar::get_regions(&state_unlocked),
+                        position.popup,
+                        region_state,
+                    );
+                }
+
+                if state_unlocked.active == Window::PlaylistPicker {
+                    // Same reason as in the table states above
+                    let playlists = unsafe { &mut (*state_ptr).account.playlists };
+                    screen.render_widget(widgets::Clear, position.popup);
+                    if let Some((_, ref mut picker_state)) = playlists {
+                        screen.render_stateful_widget(
+                            MiddleLayout::get_playlist_picker(&state_unlocked),
+                            position.popup,
+                            picker_state,
+                        );
+                    } else {
+                        screen.render_widget(
+                            MiddleLayout::get_playlist_picker(&state_unlocked),
+                            position.popup,
+                        );
+                    }
+                }
+
+                // Dropdown of suggestions is drawn over the musicbar while typing in searchbar
+                if state_unlocked.active == Window::Searchbar && !state_unlocked.suggestions.0.is_empty() {
+                    let mut area = position.suggestions;
+                    area.height = std::cmp::min(
+                        area.height,
+                        state_unlocked.suggestions.0.len() as u16 + 2,
+                    );
+                    // Same reason as in the table states above
+                    let suggestion_state = unsafe { &mut (*state_ptr).suggestions.1 };
+                    screen.render_widget(widgets::Clear, area);
+                    screen.render_stateful_widget(
+                        TopLayout::get_suggestions(&state_unlocked),
+                        area,
+                        suggestion_state,
+                    );
+                }
+
                 // Sho this popup at last after everything else is drawn.
                 // This makes sure that background is not empty and user can
                 // see some things like progress of music player
@@ -279,12 +388,16 @@ pub fn draw_ui(state: &mut Arc<Mutex<State>>, cvar: &mut Arc<Condvar>) {
 
 #[derive(Clone)]
 pub enum SidebarOption {
-    Trending = 0,
-    YoutubeCommunity = 1,
-    Liked = 2,
-    Saved = 3,
-    Following = 4,
-    Search = 5,
+    Trending(TrendingCategory),
+    YoutubeCommunity,
+    Liked,
+    Saved,
+    Following,
+    // Latest uploads of favourate artists
+    NewReleases,
+    Search,
+    // Opens the region picker
+    Region,
 }
 
 #[derive(PartialEq, Clone)]
@@ -296,6 +409,14 @@ pub enum Window {
     Artistbar,
     BottomControl,
     Popup(&'static str, String),
+    // Details of music. See DetailsState
+    Details,
+    // Captions of the music being played. See LyricsState
+    Lyrics,
+    // Picker of region to fetch trending and search result for. See region in State
+    Region,
+    // Picker of account playlist to add music to. See AccountState
+    PlaylistPicker,
     None,
 }
 
@@ -307,29 +428,107 @@ pub struct BottomState {
     // false in Some means music is paused
     // None means playing nothing. eg: At the start of program
     playing: Option<(String, bool)>,
+    // true if the music being played is a live stream. Live stream have no duration so only the
+    // elapsed time is shown and seeking is disabled
+    live: bool,
+}
+
+// State of details window
+#[derive(Default)]
+pub struct DetailsState {
+    // Id of the music whose details is to be shown. Communicator fetches the details when
+    // `details` is None
+    pub id: String,
+    pub details: Option<fetcher::details::VideoDetails>,
+    // Chapters parsed from the description and the highlighted one of them
+    pub chapters: (Vec<fetcher::details::Chapter>, ListState),
+    // Number of lines description is scrolled down
+    pub scroll: u16,
+    // Chapter selected while the music was not being played. This music is then played and
+    // seeked to given time once it starts
+    pub pending_seek: Option<Duration>,
+}
+
+// State of lyrics window. Lyrics always follow the music being played
+#[derive(Default)]
+pub struct LyricsState {
+    // Id of the music whose captions are shown. Communicator fetches the caption tracks when
+    // `tracks` is None
+    pub id: String,
+    // Caption tracks of the music and the one highlighted in language selector
+    pub tracks: Option<(Vec<fetcher::captions::CaptionTrack>, ListState)>,
+    // Index of track chosen to be shown and the track `lines` are of. Communicator fetches the
+    // lines when these two differ
+    pub chosen: Option<usize>,
+    pub lines_of: Option<usize>,
+    // Timed lines of the track and the line being sung now
+    pub lines: (Vec<fetcher::captions::CaptionLine>, ListState),
+    // Language of the track chosen last time. Same language is chosen for next music if any
+    pub language: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MusicbarSource {
-    Search(String),
-    Trending,
+    // query and the filters parsed from it
+    Search(String, SearchOptions),
+    Trending(TrendingCategory),
     RecentlyPlayed,
     Favourates,
     Playlist(String),
     Artist(String),
+    // music similar to the music of this id
+    Radio(String),
+    // Subscription feed of the account. See AccountState
+    Feed,
+    // Content of playlist of the account of this id
+    AccountPlaylist(String),
+    // Latest uploads of favourate artists. See State::seen_releases
+    NewReleases,
 }
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum PlaylistbarSource {
-    Search(String),
+    Search(String, SearchOptions),
     RecentlyPlayed,
     Favourates,
     Artist(String),
+    // Playlists of the account
+    Account,
 }
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum ArtistbarSource {
-    Search(String),
+    Search(String, SearchOptions),
     RecentlyPlayed,
     Favourates,
+    // Channels the account is subscribed to
+    Subscriptions,
+}
+
+// Change to be made in playlists of the account. Communicator sends it to the server
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccountAction {
+    // Create the playlist of this name and add the music of given id to it
+    Create(String, String),
+    // Add the music (second) to the playlist (first)
+    Add(String, String),
+    // Remove the music (second) from the playlist (first)
+    Remove(String, String),
+}
+
+// State of invidious account. When logged in, "Following" and "My playlist" in sidebar show the
+// subscriptions and playlists of the account instead of the favourates. See fetcher::account
+#[derive(Default)]
+pub struct AccountState {
+    pub logged_in: bool,
+    // Music to be added to the playlist picked in playlist picker
+    pub music_id: String,
+    // Playlists of account to pick from and the highlighted one. First item of picker is to
+    // create new playlist so the index in ListState is one more than index in Vec.
+    // Communicator fetches the playlists when this is None
+    pub playlists: Option<(Vec<fetcher::PlaylistUnit>, ListState)>,
+    // Name of new playlist as typed in playlist picker
+    pub new_name: String,
+    // Change yet to be sent to the server. Communicator takes this once it is sending
+    pub pending: Option<AccountAction>,
 }
 
 #[derive(Debug)]
@@ -343,6 +542,8 @@ pub struct PlaybackBehaviour {
     repeat: bool,
     // Current volume level. This is store here instead of fecthing with get_prop everytime
     volume: u8,
+    // true if music related to the last one in queue should be queued when queue is about to end
+    autoplay: bool,
 }
 
 pub struct State<'p> {
@@ -374,6 +575,11 @@ pub struct State<'p> {
     // second member is the string of searchbar when use pressed ENTER last time in searchbar
     pub search: (String, String),
 
+    // Suggestions to complete the query in searchbar and the state of dropdown that shows them.
+    // This is filled by communicator after user stop typing for a while. Selected suggestion
+    // replaces the query when accepted
+    pub suggestions: (Vec<String>, ListState),
+
     // Currently active window. In UI, this windows title is hilighted and keypress are evaluated
     // depending on active window
     pub active: Window,
@@ -388,4 +594,37 @@ pub struct State<'p> {
 
     // See documentation for respective struct
     pub playback_behaviour: PlaybackBehaviour,
+
+    // Source from which mpv queue is being filled and the page of it to be added next.
+    // This is either MusicbarSource::Playlist or MusicbarSource::Radio. These are not loaded at
+    // once, instead communicator appends one page at a time whenever playback comes near the end
+    // of what is already queued. None when playing neither of them or every page is queued
+    pub queue_feed: Option<(MusicbarSource, usize)>,
+
+    // Id of every music played or queued by autoplay in this session. Autoplay never queues
+    // these again
+    pub played: std::collections::HashSet<String>,
+
+    // Id of the live streams among the music added to mpv queue. mpv only knows the url of what
+    // it is playing so this is how we tell that the music being played is live
+    pub live: std::collections::HashSet<String>,
+
+    // See documentation for respective struct
+    pub details: DetailsState,
+
+    // See documentation for respective struct
+    pub lyrics: LyricsState,
+
+    // Region to fetch trending and search result for and the state of region picker. This is
+    // constants.region in config file until another region is picked. Communicator passes the
+    // change to the fetcher
+    pub region: (String, ListState),
+
+    // See documentation for respective struct
+    pub account: AccountState,
+
+    // Id of music that was already seen in new releases before it was opened this time. Rest
+    // are marked as new in musicbar. Fetcher remembers what is shown so this is only read once
+    // when new releases is opened so that the mark stays until it is opened again
+    pub seen_releases: std::collections::HashSet<String>,
 }
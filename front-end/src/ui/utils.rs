@@ -1,17 +1,70 @@
 use crate::ui;
+use fetcher::source::TrendingCategory;
 use fetcher::ExtendDuration;
 use std::borrow::Cow;
 use tui;
 use ui::shared_import::*;
 
-pub const SIDEBAR_LIST_COUNT: usize = 6;
+// Maximum number of suggestions visible at once in the dropdown below searchbar
+pub const SUGGESTION_LIST_HEIGHT: u16 = 8;
+pub const SIDEBAR_LIST_COUNT: usize = 12;
 pub const SIDEBAR_LIST_ITEMS: [&str; SIDEBAR_LIST_COUNT] = [
+    "Trending music",
     "Trending",
+    "Trending gaming",
+    "Trending movies",
+    "Popular",
     "Youtube Community",
     "Liked songs",
     "My playlist",
     "Following",
+    "New releases",
     "Search",
+    "Region",
+];
+// Regions that can be picked in region picker as (ISO code, name). Region in config file can
+// still be anything that server accepts
+pub const REGIONS: [(&str, &str); 40] = [
+    ("AR", "Argentina"),
+    ("AU", "Australia"),
+    ("BD", "Bangladesh"),
+    ("BR", "Brazil"),
+    ("CA", "Canada"),
+    ("CL", "Chile"),
+    ("CO", "Colombia"),
+    ("DE", "Germany"),
+    ("EG", "Egypt"),
+    ("ES", "Spain"),
+    ("FR", "France"),
+    ("GB", "United Kingdom"),
+    ("ID", "Indonesia"),
+    ("IE", "Ireland"),
+    ("IN", "India"),
+    ("IT", "Italy"),
+    ("JP", "Japan"),
+    ("KE", "Kenya"),
+    ("KR", "South Korea"),
+    ("LK", "Sri Lanka"),
+    ("MX", "Mexico"),
+    ("MY", "Malaysia"),
+    ("NG", "Nigeria"),
+    ("NL", "Netherlands"),
+    ("NP", "Nepal"),
+    ("NZ", "New Zealand"),
+    ("PH", "Philippines"),
+    ("PK", "Pakistan"),
+    ("PL", "Poland"),
+    ("PT", "Portugal"),
+    ("RU", "Russia"),
+    ("SA", "Saudi Arabia"),
+    ("SE", "Sweden"),
+    ("SG", "Singapore"),
+    ("TH", "Thailand"),
+    ("TR", "Turkey"),
+    ("UA", "Ukraine"),
+    ("US", "United States"),
+    ("VN", "Vietnam"),
+    ("ZA", "South Africa"),
 ];
 use config::initilize::{
     CONFIG, STORAGE, TB_FAVOURATES_ARTIST, TB_FAVOURATES_MUSIC, TB_FAVOURATES_PLAYLIST,
@@ -91,6 +144,24 @@ impl<'parent> ui::TopLayout {
         ]);
         Paragraph::new(text).block(block)
     }
+
+    pub fn get_suggestions(state: &'parent ui::State) -> List<'parent> {
+        List::new(
+            state
+                .suggestions
+                .0
+                .iter()
+                .map(|suggestion| {
+                    ListItem::new(Span::styled(
+                        suggestion.as_str(),
+                        Style::list_idle().fg(rgb!(CONFIG.theme.color_primary)),
+                    ))
+                })
+                .collect::<Vec<ListItem>>(),
+        )
+        .highlight_style(Style::list_highlight())
+        .block(Block::active("Suggestions ".to_owned()))
+    }
 }
 
 impl<'parent> ui::MainLayout {
@@ -130,14 +201,27 @@ impl<'parent> ui::MiddleLayout {
         };
 
         let data_list = &state.musicbar.0;
+        let new_releases = state.filled_source.0 == ui::MusicbarSource::NewReleases;
         let items: Vec<Row> = data_list
             .iter()
             .map(|music| {
-                Row::new(vec![
-                    music.name.as_str(),
-                    music.artist.as_str(),
-                    music.duration.as_str(),
-                ])
+                let name = if new_releases && !state.seen_releases.contains(&music.id) {
+                    Cell::from(Spans::from(vec![
+                        Span::styled("NEW ", Style::list_highlight().add_modifier(Modifier::BOLD)),
+                        Span::raw(music.name.as_str()),
+                    ]))
+                } else {
+                    Cell::from(music.name.as_str())
+                };
+                let length = if music.live {
+                    Cell::from(Span::styled(
+                        "LIVE",
+                        Style::list_highlight().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                    ))
+                } else {
+                    Cell::from(ExtendDuration::to_string(music.duration))
+                };
+                Row::new(vec![name, Cell::from(music.artist.as_str()), length])
             })
             .collect();
         let table = Table::new(items)
@@ -157,6 +241,176 @@ impl<'parent> ui::MiddleLayout {
 
         table
     }
+
+    // Playlists of account to add the music to. First item creates new playlist of the name
+    // typed while picker is open
+    pub fn get_playlist_picker(state: &'parent ui::State) -> List<'parent> {
+        let mut items = vec![ListItem::new(format!(
+            "+ New playlist: {}",
+            state.account.new_name
+        ))];
+        match state.account.playlists {
+            Some((ref playlists, _)) => items.extend(playlists.iter().map(|playlist| {
+                ListItem::new(format!("  {} ({})", playlist.name, playlist.video_count))
+            })),
+            None => items.push(ListItem::new("  Loading playlists..")),
+        }
+
+        List::new(items)
+            .style(Style::list_idle())
+            .highlight_style(Style::list_highlight())
+            .block(Block::active("Add to playlist ".to_owned()))
+    }
+}
+
+impl<'parent> ui::DetailsLayout {
+    pub fn new(parent: Rect) -> Self {
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
+            .split(parent);
+
+        ui::DetailsLayout {
+            layout: [layout[0], layout[1]],
+        }
+    }
+
+    // Desired layout:
+    // | <title>
+    // | Channel: <channel> (<subscribers> subscribers)
+    // | Views: <views>  Likes: <likes>
+    // | Published: <published>  Genre: <genre>
+    // | Url: <url>
+    // |
+    // | <description...>
+    pub fn get_info(state: &'parent ui::State) -> Paragraph<'parent> {
+        let block = Block::active("Details ".to_owned());
+        let details = match state.details.details {
+            Some(ref details) => details,
+            None => {
+                return Paragraph::new(Span::styled("Loading..", Style::list_idle())).block(block)
+            }
+        };
+
+        let field = |name: &'static str, value: String| {
+            vec![
+                Span::styled(name, Style::list_title()),
+                Span::styled(value, Style::list_idle()),
+            ]
+        };
+        let mut lines = vec![
+            Spans::from(Span::styled(
+                details.title.as_str(),
+                Style::list_highlight(),
+            )),
+            Spans::from(field(
+                "Channel: ",
+                format!("{} ({} subscribers)", details.channel, details.subscribers),
+            )),
+            Spans::from(
+                [
+                    field("Views: ", details.views.to_string()),
+                    field("  Likes: ", details.likes.to_string()),
+                ]
+                .concat(),
+            ),
+            Spans::from(
+                [
+                    field("Published: ", details.published.clone()),
+                    field("  Genre: ", details.genre.clone()),
+                ]
+                .concat(),
+            ),
+            Spans::from(field("Url: ", format!("https://youtu.be/{}", details.id))),
+            Spans::default(),
+        ];
+        lines.extend(
+            details
+                .description
+                .lines()
+                .map(|line| Spans::from(Span::raw(line))),
+        );
+
+        Paragraph::new(Text::from(lines))
+            .wrap(widgets::Wrap { trim: false })
+            .scroll((state.details.scroll, 0))
+            .block(block)
+    }
+
+    pub fn get_chapters(state: &'parent ui::State) -> List<'parent> {
+        let items: Vec<ListItem> = state
+            .details
+            .chapters
+            .0
+            .iter()
+            .map(|chapter| {
+                ListItem::new(format!(
+                    "{} {}",
+                    ExtendDuration::to_string(chapter.start),
+                    chapter.title
+                ))
+            })
+            .collect();
+
+        List::new(items)
+            .style(Style::list_idle())
+            .highlight_style(Style::list_highlight())
+            .block(Block::new("Chapters ".to_owned()))
+    }
+
+    // Lines of the chosen caption track. Line being sung is highlighted
+    pub fn get_lyrics(state: &'parent ui::State) -> List<'parent> {
+        let lyrics = &state.lyrics;
+        let message = if lyrics.id.is_empty() {
+            Some("Play some music to see its lyrics..")
+        } else {
+            match lyrics.tracks {
+                None => Some("Loading.."),
+                Some((ref tracks, _)) if tracks.is_empty() => Some("No captions for this music.."),
+                Some(_) if lyrics.chosen != lyrics.lines_of => Some("Loading.."),
+                Some(_) => None,
+            }
+        };
+
+        let items: Vec<ListItem> = match message {
+            Some(message) => vec![ListItem::new(message)],
+            None => lyrics
+                .lines
+                .0
+                .iter()
+                .map(|line| ListItem::new(line.text.as_str()))
+                .collect(),
+        };
+
+        List::new(items)
+            .style(Style::list_idle())
+            .highlight_style(Style::list_highlight())
+            .block(Block::active("Lyrics ".to_owned()))
+    }
+
+    // Language selector. Chosen track is marked with `*`
+    pub fn get_caption_languages(state: &'parent ui::State) -> List<'parent> {
+        let items: Vec<ListItem> = match state.lyrics.tracks {
+            Some((ref tracks, _)) => tracks
+                .iter()
+                .enumerate()
+                .map(|(index, track)| {
+                    let mark = if state.lyrics.chosen == Some(index) {
+                        "*"
+                    } else {
+                        " "
+                    };
+                    ListItem::new(format!("{} {}", mark, track.label))
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        List::new(items)
+            .style(Style::list_idle())
+            .highlight_style(Style::list_highlight())
+            .block(Block::new("Languages ".to_owned()))
+    }
 }
 
 impl<'parent> ui::MiddleBottom {
@@ -241,9 +495,10 @@ impl<'parent> ui::SideBar {
         // | <playing | paused>
         // | R-1
         // | S-1
+        // | A-1
         // ----------------
-        // Total height: 6
-        let status_height: u16 = 6;
+        // Total height: 7
+        let status_height: u16 = 7;
         let list_height = parent.height.checked_sub(status_height).unwrap_or_default();
 
         let layout = Layout::default()
@@ -267,9 +522,17 @@ impl<'parent> ui::SideBar {
         List::new(
             SIDEBAR_LIST_ITEMS
                 .iter()
-                .map(|v| {
+                .enumerate()
+                .map(|(index, v)| {
+                    // Show the region being used along with the entry to pick it
+                    let item = match ui::SidebarOption::try_from(index) {
+                        Ok(ui::SidebarOption::Region) => {
+                            Cow::Owned(format!("{} ({})", v, state.region.0))
+                        }
+                        _ => Cow::Borrowed(*v),
+                    };
                     ListItem::new(Span::styled(
-                        *v,
+                        item,
                         Style::list_idle().fg(rgb!(CONFIG.theme.color_primary)),
                     ))
                 })
@@ -278,6 +541,22 @@ impl<'parent> ui::SideBar {
         .highlight_style(Style::list_highlight())
         .block(block)
     }
+
+    // List of regions to pick from. Region being used is marked with `*`
+    pub fn get_regions(state: &'parent ui::State) -> List<'parent> {
+        let items: Vec<ListItem> = REGIONS
+            .iter()
+            .map(|(code, name)| {
+                let mark = if state.region.0 == *code { "*" } else { " " };
+                ListItem::new(format!("{} {} {}", mark, code, name))
+            })
+            .collect();
+
+        List::new(items)
+            .style(Style::list_idle())
+            .highlight_style(Style::list_highlight())
+            .block(Block::active("Region ".to_owned()))
+    }
 }
 
 impl<'parent> ui::BottomLayout {
@@ -298,11 +577,16 @@ impl<'parent> ui::BottomLayout {
             content = ">> Play some Music <<"
         };
 
-        let heading = format!(
-            "{} / {}",
-            state.bottom.music_elapse.to_string(),
-            state.bottom.music_duration.to_string()
-        );
+        // Live stream have no duration to show or to fill the gauge upto
+        let heading = if state.bottom.live {
+            format!("LIVE {}", state.bottom.music_elapse.to_string())
+        } else {
+            format!(
+                "{} / {}",
+                state.bottom.music_elapse.to_string(),
+                state.bottom.music_duration.to_string()
+            )
+        };
 
         let mut block;
         if state.active == ui::Window::BottomControl {
@@ -316,7 +600,7 @@ impl<'parent> ui::BottomLayout {
             state.bottom.music_elapse.as_secs_f64() / state.bottom.music_duration.as_secs_f64();
         if ratio > 1.0 {
             ratio = 1.0
-        } else if ratio.is_nan() || ratio < 0.0 {
+        } else if ratio.is_nan() || ratio < 0.0 || state.bottom.live {
             ratio = 0.0
         }
 
@@ -334,6 +618,7 @@ impl<'parent> ui::BottomLayout {
     // | Vol: <volume_level>
     // | suffle | <strikethrough>suffle<strikethrough>
     // | (no-)repeat
+    // | autoplay | <strikethrough>autoplay<strikethrough>
     // | playing | paused (blinked)
     pub fn get_icons_set(state: &'parent ui::State) -> Paragraph<'parent> {
         let block = Block::active(String::new());
@@ -357,6 +642,11 @@ impl<'parent> ui::BottomLayout {
             suffle.style = suffle.style.add_modifier(Modifier::CROSSED_OUT);
         }
 
+        let mut autoplay = Span::styled("autoplay", Style::list_highlight());
+        if !state.playback_behaviour.autoplay {
+            autoplay.style = autoplay.style.add_modifier(Modifier::CROSSED_OUT);
+        }
+
         let volume = Span::styled(
             format!("Vol: {}", state.playback_behaviour.volume),
             Style::list_highlight(),
@@ -367,6 +657,7 @@ impl<'parent> ui::BottomLayout {
                 Spans([volume].to_vec()),
                 Spans([repeat].to_vec()),
                 Spans([suffle].to_vec()),
+                Spans([autoplay].to_vec()),
                 Spans([paused_status].to_vec()),
             ]
             .to_vec(),
@@ -477,8 +768,25 @@ impl ui::Position {
             width,
         };
 
+        // Right below the searchbar and over the musicbar
+        let search_pos = top_section.layout[0];
+        let suggestions_pos = Rect {
+            x: search_pos.x,
+            y: search_pos.y + search_pos.height,
+            width: search_pos.width,
+            height: std::cmp::min(SUGGESTION_LIST_HEIGHT + 2, for_middle),
+        };
+
+        // Whole middle section
+        let details_section = ui::DetailsLayout::new(
+            middle_section
+                .layout
+                .union(middle_bottom.layout[0])
+                .union(middle_bottom.layout[1]),
+        );
+
         ui::Position {
-            search: top_section.layout[0],
+            search: search_pos,
             status: top_section.layout[1],
             shortcut: sidebar.layout[0],
             music: middle_section.layout,
@@ -487,6 +795,9 @@ impl ui::Position {
             music_info: bottom_section.layout,
             bottom_icons: sidebar.layout[1],
             popup: popup_pos,
+            suggestions: suggestions_pos,
+            details_info: details_section.layout[0],
+            details_chapters: details_section.layout[1],
         }
     }
 }
@@ -495,6 +806,7 @@ impl Default for ui::State<'_> {
     fn default() -> Self {
         let mpv = libmpv::Mpv::new().unwrap();
         mpv.configure_defult();
+        mpv.use_network(&CONFIG.network);
         mpv.cache_for(10);
         // By default repeat the playlist. Set playlist to repeat
         mpv.repeat_playlist();
@@ -511,6 +823,7 @@ impl Default for ui::State<'_> {
             playlistbar: (Vec::new(), TableState::default()),
             artistbar: (Vec::new(), TableState::default()),
             search: (String::new(), String::new()),
+            suggestions: (Vec::new(), ListState::default()),
             active: ui::Window::Sidebar,
             fetched_page: [None; 3],
             filled_source: (
@@ -522,19 +835,46 @@ impl Default for ui::State<'_> {
                 playing: None,
                 music_duration: Duration::new(0, 0),
                 music_elapse: Duration::new(0, 0),
+                live: false,
             },
             player: mpv,
             playback_behaviour: ui::PlaybackBehaviour {
                 shuffle: false,
                 repeat: true,
                 volume: 100,
+                autoplay: false,
             },
+            queue_feed: None,
+            played: Default::default(),
+            live: Default::default(),
+            details: Default::default(),
+            lyrics: Default::default(),
+            region: {
+                let mut picker = ListState::default();
+                picker.select(
+                    REGIONS
+                        .iter()
+                        .position(|(code, _)| *code == CONFIG.constants.region),
+                );
+                (CONFIG.constants.region.clone(), picker)
+            },
+            account: ui::AccountState {
+                logged_in: fetcher::account::load(
+                    &STORAGE,
+                    CONFIG.servers.list.iter().map(|server| &server.url),
+                )
+                .iter()
+                .any(Option::is_some),
+                ..Default::default()
+            },
+            seen_releases: Default::default(),
         }
     }
 }
 
 pub trait ExtendMpv {
     fn configure_defult(&self);
+    fn use_network(&self, network: &config::Network);
     fn repeat_playlist(&self);
     fn repeat_one(&self);
     fn repeat_nothing(&self);
@@ -561,6 +901,26 @@ impl ExtendMpv for libmpv::Mpv {
         self.set_property("video", "no").unwrap();
     }
 
+    fn use_network(&self, network: &config::Network) {
+        // mpv itself only speaks http proxy. Running with only socks proxy is confirmed in cli
+        if !network.http_proxy.trim().is_empty() {
+            self.set_property("http-proxy", network.http_proxy.trim())
+                .ok();
+            // ffmpeg that opens the stream for mpv has no option for this but reads the env
+            // when connecting. This runs before any other thread is spawned
+            if !network.no_proxy.is_empty() {
+                std::env::set_var("no_proxy", network.no_proxy.join(","));
+            }
+        }
+        // youtube-dl that mpv runs to get the stream url. Appended so that options from
+        // mpv.conf are kept
+        if let Some(proxy) = network.proxy() {
+            let option = format!("proxy={}", proxy);
+            self.command("change-list", &["ytdl-raw-options", "append", &option])
+                .ok();
+        }
+    }
+
     #[inline(always)]
     fn shuffle(&self) {
         self.command("playlist-shuffle", &[]).ok();
@@ -632,6 +992,8 @@ impl ExtendMpv for libmpv::Mpv {
 
 impl ui::State<'_> {
     pub fn play_music(&mut self, music_id: &str) {
+        // queue is replaced below so stop feeding the previous playlist or radio if any
+        self.queue_feed = None;
         self.player.unpause().ok();
         match self.player.command(
             "loadfile",
@@ -641,6 +1003,7 @@ impl ui::State<'_> {
                 // clear any previous thing from bottombar
                 self.bottom.music_duration = Duration::from_secs(0);
                 self.bottom.music_elapse = Duration::from_secs(0);
+                self.bottom.live = false;
 
                 self.status = "Playing...";
                 // set currently playing (unpaused) to ture. no need to set real title as it will
@@ -652,6 +1015,9 @@ impl ui::State<'_> {
         // Now as the selection is being played. Add remaining item from musicbar to the play
         // queue.
         for music in self.musicbar.0.iter() {
+            if music.live {
+                self.live.insert(music.id.clone());
+            }
             // If this is the currently payed song donot add it to prevent having
             // currently played song two time in queue
             if music.id == *music_id {
@@ -670,12 +1036,21 @@ impl ui::State<'_> {
         }
     }
 
-    // This function is called when user press enter in non-empty list of playlistbar
-    pub fn activate_playlist(&mut self, playlist_id: &str) {
-        match self.player.command(
-            "loadfile",
-            [format!("https://www.youtube.com/playlist?list={}", playlist_id).as_str()].as_ref(),
-        ) {
+    // This function is called when user press enter in non-empty list of playlistbar.
+    // source is either MusicbarSource::Playlist or MusicbarSource::AccountPlaylist
+    pub fn activate_playlist(&mut self, source: ui::MusicbarSource) {
+        self.feed_queue_from(source);
+    }
+
+    // Play the radio started from given music
+    pub fn start_radio(&mut self, music_id: &str) {
+        self.feed_queue_from(ui::MusicbarSource::Radio(music_id.to_string()));
+    }
+
+    // Nothing is loaded here. This only clears the queue and communicator then keeps adding the
+    // content of `source` to it page by page. See queue_feed in State
+    fn feed_queue_from(&mut self, source: ui::MusicbarSource) {
+        match self.player.command("stop", &[]) {
             Ok(_) => {
                 // send unpause signal
                 self.player.unpause().ok();
@@ -683,8 +1058,10 @@ impl ui::State<'_> {
                 // clear any previous thing from bottombar
                 self.bottom.music_duration = Duration::from_secs(0);
                 self.bottom.music_elapse = Duration::from_secs(0);
+                self.bottom.live = false;
 
-                self.status = "Playing..";
+                self.status = "Loading..";
+                self.queue_feed = Some((source, 0));
                 // set currently playing (unpaused) to ture. no need to set real title as it will
                 // be done by refresh_mpv_status() later on
                 self.bottom.playing = Some((String::new(), true));
@@ -693,6 +1070,265 @@ impl ui::State<'_> {
         }
     }
 
+    // Source and the page of it that should be queued now. This is Some only when less than a
+    // page of music is left to play in the queue
+    pub fn queue_feed_wants(&self) -> Option<(ui::MusicbarSource, usize)> {
+        let (source, page) = self.queue_feed.as_ref()?;
+        let count = self
+            .player
+            .get_property::<i64>("playlist-count")
+            .unwrap_or_default();
+        // -1 when nothing is being played
+        let position = self
+            .player
+            .get_property::<i64>("playlist-pos")
+            .unwrap_or(-1);
+
+        if count - position - 1 < CONFIG.constants.item_per_list as i64 {
+            Some((source.clone(), *page))
+        } else {
+            None
+        }
+    }
+
+    // Append the page fetched for queue_feed to the mpv queue. Playback is started from the
+    // first of them if nothing is being played
+    pub fn queue_feed_page(&mut self, musics: &[fetcher::MusicUnit]) {
+        for music in musics {
+            if music.live {
+                self.live.insert(music.id.clone());
+            }
+            self.player
+                .command(
+                    "loadfile",
+                    [
+                        format!("https://www.youtube.com/watch?v={}", music.id).as_str(),
+                        "append-play",
+                    ]
+                    .as_ref(),
+                )
+                .ok();
+        }
+        if let Some((_, ref mut page)) = self.queue_feed {
+            *page += 1;
+        }
+        self.status = "Playing..";
+    }
+
+    // Show the details window for given music. Details already fetched is reused if it is of
+    // same music
+    pub fn show_details(&mut self, music_id: &str) {
+        if self.details.id != *music_id {
+            self.details = ui::DetailsState {
+                id: music_id.to_string(),
+                ..Default::default()
+            };
+        }
+        self.active = ui::Window::Details;
+    }
+
+    // Fill the details window with the details fetched by communicator
+    pub fn fill_details(&mut self, details: fetcher::details::VideoDetails) {
+        self.details.chapters.0 = details.chapters();
+        self.details.chapters.1.select(None);
+        self.details.scroll = 0;
+        self.details.details = Some(details);
+    }
+
+    // Seek to the highlighted chapter in details window. If the music of details is not being
+    // played, play it first and seek once it starts. See refresh_mpv_status()
+    pub fn play_chapter(&mut self) {
+        let start = match self.details.chapters.1.selected() {
+            Some(index) => self.details.chapters.0[index].start,
+            None => return,
+        };
+
+        if self.playing_music_id().as_ref() == Some(&self.details.id) {
+            self.seek_to(start);
+        } else {
+            let music_id = self.details.id.clone();
+            self.play_music(&music_id);
+            self.details.pending_seek = Some(start);
+        }
+    }
+
+    fn seek_to(&mut self, time: Duration) {
+        if self.bottom.live {
+            self.status = "Cannot seek live..";
+            return;
+        }
+        match self
+            .player
+            .command("seek", &[&time.as_secs().to_string(), "absolute"])
+        {
+            Ok(_) => self.status = "Seeked..",
+            Err(_) => self.status = "Seek error..",
+        }
+    }
+
+    // Id of the music whose related music should be queued by autoplay now. This is Some only
+    // when autoplay is on, last music in queue is being played and the queue is not being fed
+    // by playlist or radio
+    pub fn autoplay_wants(&self) -> Option<String> {
+        if !self.playback_behaviour.autoplay || self.queue_feed.is_some() {
+            return None;
+        }
+        let count = self
+            .player
+            .get_property::<i64>("playlist-count")
+            .unwrap_or_default();
+        let position = self
+            .player
+            .get_property::<i64>("playlist-pos")
+            .unwrap_or(-1);
+
+        if position >= 0 && position + 1 >= count {
+            self.playing_music_id()
+        } else {
+            None
+        }
+    }
+
+    // Append the music fetched by autoplay to mpv queue skipping the one already played or
+    // queued
+    pub fn queue_autoplay(&mut self, musics: &[fetcher::MusicUnit]) {
+        let mut queued = 0;
+        for music in musics {
+            if !self.played.insert(music.id.clone()) {
+                continue;
+            }
+            if music.live {
+                self.live.insert(music.id.clone());
+            }
+            self.player
+                .command(
+                    "loadfile",
+                    [
+                        format!("https://www.youtube.com/watch?v={}", music.id).as_str(),
+                        "append",
+                    ]
+                    .as_ref(),
+                )
+                .ok();
+            queued += 1;
+        }
+
+        self.status = if queued == 0 {
+            "Nothing new to autoplay.."
+        } else {
+            "Autoplay queued.."
+        };
+    }
+
+    // Open the playlist picker to add the music focused in musicbar to a playlist of account
+    pub fn open_playlist_picker(&mut self) {
+        if !self.account.logged_in {
+            self.status = "Not logged in..";
+            return;
+        }
+        let selected = self
+            .musicbar
+            .1
+            .selected()
+            .and_then(|index| self.musicbar.0.get(index));
+        match selected {
+            Some(music) => {
+                self.account.music_id = music.id.clone();
+                // Playlists may have changed since the picker was opened last time
+                self.account.playlists = None;
+                self.active = ui::Window::PlaylistPicker;
+            }
+            None => self.status = "Nothing selected..",
+        }
+    }
+
+    pub fn fill_playlist_picker(&mut self, playlists: Vec<fetcher::PlaylistUnit>) {
+        let mut picker = ListState::default();
+        picker.select(Some(0));
+        self.account.playlists = Some((playlists, picker));
+    }
+
+    // Add the music to the playlist highlighted in picker. Playlist is created first when
+    // the first item is highlighted. Communicator then sends the change to the server
+    pub fn pick_playlist(&mut self) {
+        let music_id = self.account.music_id.clone();
+        let action = match self.account.playlists {
+            Some((ref playlists, ref picker)) => match picker.selected() {
+                Some(0) | None => {
+                    let name = self.account.new_name.trim();
+                    if name.is_empty() {
+                        self.status = "Type a name..";
+                        return;
+                    }
+                    ui::AccountAction::Create(name.to_string(), music_id)
+                }
+                Some(index) => match playlists.get(index - 1) {
+                    Some(playlist) => ui::AccountAction::Add(playlist.id.clone(), music_id),
+                    None => return,
+                },
+            },
+            // Still loading
+            None => return,
+        };
+
+        self.account.pending = Some(action);
+        self.account.new_name.clear();
+        self.status = "Saving..";
+        self.active = ui::Window::Musicbar;
+    }
+
+    // Remove the music focused in musicbar from the playlist of account shown there
+    pub fn remove_from_account_playlist(&mut self) {
+        let playlist_id = match self.filled_source.0 {
+            ui::MusicbarSource::AccountPlaylist(ref playlist_id) => playlist_id.clone(),
+            _ => {
+                self.status = "Not your playlist..";
+                return;
+            }
+        };
+        let selected = self
+            .musicbar
+            .1
+            .selected()
+            .and_then(|index| self.musicbar.0.get(index));
+        match selected {
+            Some(music) => {
+                self.account.pending =
+                    Some(ui::AccountAction::Remove(playlist_id, music.id.clone()));
+                self.status = "Saving..";
+            }
+            None => self.status = "Nothing selected..",
+        }
+    }
+
+    // Change was saved in the server. Removed music is also removed from musicbar if that
+    // playlist is still shown there
+    pub fn account_action_done(&mut self, action: &ui::AccountAction) {
+        // Picker is filled again next time with the change
+        self.account.playlists = None;
+        match action {
+            ui::AccountAction::Create(..) => self.status = "Created..",
+            ui::AccountAction::Add(..) => self.status = "Added..",
+            ui::AccountAction::Remove(playlist_id, music_id) => {
+                self.status = "Removed..";
+                if self.filled_source.0 == ui::MusicbarSource::AccountPlaylist(playlist_id.clone())
+                {
+                    if let Some(index) = self.musicbar.0.iter().position(|m| m.id == *music_id) {
+                        self.musicbar.0.remove(index);
+                        self.musicbar.1.select(None);
+                    }
+                }
+            }
+        }
+    }
+
+    // Id of the music being played by mpv. None when nothing is loaded
+    pub fn playing_music_id(&self) -> Option<String> {
+        let path = self.player.get_property::<String>("path").ok()?;
+        path.strip_prefix("https://www.youtube.com/watch?v=")
+            .map(|id| id.to_string())
+    }
+
     // This function can also be used to check playing status
     // Returning true means some music is playing which may be paused or unpaused
     pub fn refresh_mpv_status(&mut self) {
@@ -729,9 +1365,72 @@ impl ui::State<'_> {
                 .unwrap_or_default();
 
             self.bottom.playing = Some((title, true)); // at this scope of match playing status is always true
+
+            // Remember what is played so that autoplay do not queue it again
+            if let Some(music_id) = self.playing_music_id() {
+                // Chapter was selected before this music started. Seek only once mpv knows
+                // the duration, before that there is nothing to seek into
+                if music_id == self.details.id && self.bottom.music_duration.as_secs() > 0 {
+                    if let Some(start) = self.details.pending_seek.take() {
+                        self.seek_to(start);
+                    }
+                }
+                self.bottom.live = self.live.contains(&music_id);
+                self.played.insert(music_id);
+            }
             self.bottom.music_duration =
                 Duration::from_secs(estimated_duration_reply.try_into().unwrap_or_default());
         }
+        self.sync_lyrics();
+    }
+
+    // Make lyrics follow the music being played and highlight the line being sung
+    fn sync_lyrics(&mut self) {
+        if let Some(music_id) = self.playing_music_id() {
+            if music_id != self.lyrics.id {
+                self.lyrics = ui::LyricsState {
+                    id: music_id,
+                    language: std::mem::take(&mut self.lyrics.language),
+                    ..Default::default()
+                };
+            }
+        }
+        let current =
+            fetcher::captions::current_line(&self.lyrics.lines.0, self.bottom.music_elapse);
+        self.lyrics.lines.1.select(current);
+    }
+
+    // Fill the language selector with the tracks fetched by communicator. Track of the language
+    // chosen last time is chosen if there is one, otherwise the first one
+    pub fn fill_caption_tracks(&mut self, tracks: Vec<fetcher::captions::CaptionTrack>) {
+        let chosen = tracks
+            .iter()
+            .position(|track| track.language == self.lyrics.language)
+            .or(if tracks.is_empty() { None } else { Some(0) });
+        let mut selector = ListState::default();
+        selector.select(chosen);
+        self.lyrics.tracks = Some((tracks, selector));
+        self.lyrics.chosen = chosen;
+        self.lyrics.lines_of = None;
+    }
+
+    // Fill the lyrics window with lines of track at `index` fetched by communicator
+    pub fn fill_lyrics(&mut self, index: usize, lines: Vec<fetcher::captions::CaptionLine>) {
+        self.lyrics.lines = (lines, ListState::default());
+        self.lyrics.lines_of = Some(index);
+        self.sync_lyrics();
+    }
+
+    // Show the lyrics of track highlighted in language selector
+    pub fn choose_caption_track(&mut self) {
+        let tracks = match self.lyrics.tracks {
+            Some((ref tracks, ref selector)) => selector.selected().map(|index| (index, tracks)),
+            None => None,
+        };
+        if let Some((index, tracks)) = tracks {
+            self.lyrics.language = tracks[index].language.clone();
+            self.lyrics.chosen = Some(index);
+        }
     }
 
     pub fn toggle_pause(&mut self) {
@@ -846,11 +1545,17 @@ impl ui::State<'_> {
             tb_name = TB_FAVOURATES_MUSIC
         );
 
+        // Duration is saved as text. Live stream have no duration so `LIVE` is saved instead
+        let duration = if music.live {
+            "LIVE".to_string()
+        } else {
+            ExtendDuration::to_string(music.duration)
+        };
         let args = [
             (":id", &music.id),
             (":title", &music.name),
             (":author", &music.artist),
-            (":duration", &music.duration),
+            (":duration", &duration),
         ];
 
         let res = STORAGE.lock().unwrap().execute(&query, &args);
@@ -897,7 +1602,11 @@ impl ui::Window {
             ui::Window::Searchbar
             | ui::Window::Artistbar
             | ui::Window::BottomControl
-            | ui::Window::Popup(..) => ui::Window::Sidebar,
+            | ui::Window::Popup(..)
+            | ui::Window::Details
+            | ui::Window::Lyrics
+            | ui::Window::Region
+            | ui::Window::PlaylistPicker => ui::Window::Sidebar,
             ui::Window::None => unreachable!(),
         }
     }
@@ -910,7 +1619,11 @@ impl ui::Window {
             ui::Window::Searchbar
             | ui::Window::Sidebar
             | ui::Window::BottomControl
-            | ui::Window::Popup(..) => ui::Window::Artistbar,
+            | ui::Window::Popup(..)
+            | ui::Window::Details
+            | ui::Window::Lyrics
+            | ui::Window::Region
+            | ui::Window::PlaylistPicker => ui::Window::Artistbar,
             ui::Window::None => unreachable!(),
         }
     }
@@ -920,12 +1633,18 @@ impl std::convert::TryFrom<usize> for ui::SidebarOption {
     type Error = &'static str;
     fn try_from(value: usize) -> Result<Self, Self::Error> {
         match value {
-            0 => Ok(ui::SidebarOption::Trending),
-            1 => Ok(ui::SidebarOption::YoutubeCommunity),
-            2 => Ok(ui::SidebarOption::Liked),
-            3 => Ok(ui::SidebarOption::Saved),
-            4 => Ok(ui::SidebarOption::Following),
-            5 => Ok(ui::SidebarOption::Search),
+            0 => Ok(ui::SidebarOption::Trending(TrendingCategory::Music)),
+            1 => Ok(ui::SidebarOption::Trending(TrendingCategory::Default)),
+            2 => Ok(ui::SidebarOption::Trending(TrendingCategory::Gaming)),
+            3 => Ok(ui::SidebarOption::Trending(TrendingCategory::Movies)),
+            4 => Ok(ui::SidebarOption::Trending(TrendingCategory::Popular)),
+            5 => Ok(ui::SidebarOption::YoutubeCommunity),
+            6 => Ok(ui::SidebarOption::Liked),
+            7 => Ok(ui::SidebarOption::Saved),
+            8 => Ok(ui::SidebarOption::Following),
+            9 => Ok(ui::SidebarOption::NewReleases),
+            10 => Ok(ui::SidebarOption::Search),
+            11 => Ok(ui::SidebarOption::Region),
             _ => Err("No sidebar option found corresponding to this usize"),
         }
     }